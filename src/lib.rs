@@ -2,6 +2,8 @@ pub mod nics;
 pub mod node;
 pub mod simulator;
 pub mod message;
+pub mod rng;
+pub mod testing;
 
 #[cfg(test)]
 mod tests;
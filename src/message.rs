@@ -1,13 +1,198 @@
-use std::{future::Future, pin::Pin, task::Poll};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    ops::Deref,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
 
-use crate::nics::{Nic, NicId};
+use smoltcp::wire::EthernetAddress;
 
+use self::frame::Frame;
+use crate::{
+    nics::{MulticastGroup, Nic, NicId, Nics},
+    node::NodeError,
+};
+
+pub mod ack;
+mod crc;
+pub mod fragment;
+pub mod frame;
+pub mod sequence;
+
+/// How far ahead `Mailbox::link_utilization` looks, in nanoseconds: one millisecond.
+pub const UTILIZATION_WINDOW: u64 = 1_000_000;
+
+/// The TTL every message starts out with, so it can be forwarded this many times.
+pub const DEFAULT_TTL: u8 = 64;
+
+/// Numbers each frame the simulation sends, in the order they are sent, so it can be told apart from others.
+/// Forwarded frames keep theirs, and copies made by a link share the original's.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct MsgId(pub u64);
+
+/// Message bytes, either owned by a single message or shared by the copies of a broadcast.
+#[derive(Debug, Clone)]
+pub(crate) enum Payload {
+    Owned(Vec<u8>),
+    Shared(Arc<[u8]>),
+}
+
+impl Payload {
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        match self {
+            Payload::Owned(data) => data,
+            Payload::Shared(data) => data,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+}
+
+impl PartialEq for Payload {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for Payload {}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        Payload::Owned(data)
+    }
+}
+
+impl From<Arc<[u8]>> for Payload {
+    fn from(data: Arc<[u8]>) -> Self {
+        Payload::Shared(data)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct IncomingMsg {
     from: NicId,
-    data: Pin<Vec<u8>>,
+    to: NicId,
+    data: Payload,
+    priority: u8,
+    /// CRC-32 of the data as it was sent, if it came over a link.
+    checksum: Option<u32>,
+    ttl: u8,
+    id: MsgId,
 }
 
-impl IncomingMsg {}
+impl IncomingMsg {
+    pub(crate) fn new(from: NicId, to: NicId, data: impl Into<Payload>) -> Self {
+        Self {
+            from,
+            to,
+            data: data.into(),
+            priority: 0,
+            checksum: None,
+            ttl: DEFAULT_TTL,
+            id: MsgId::default(),
+        }
+    }
+
+    /// A message as if nic `from` had sent `data` to nic `to`, for `Sim::with_initial_messages`.
+    /// Neither nic needs to be in the simulation.
+    pub fn injected(from: NicId, to: NicId, data: Vec<u8>) -> Self {
+        Self::new(from, to, data)
+    }
+
+    pub(crate) fn with_to(mut self, to: NicId) -> Self {
+        self.to = to;
+        self
+    }
+
+    pub(crate) fn with_id(mut self, id: MsgId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub(crate) fn with_ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub(crate) fn with_checksum(mut self, checksum: u32) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
+    pub(crate) fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// The nic that sent the message.
+    pub fn from(&self) -> NicId {
+        self.from
+    }
+
+    /// The local nic the message was received on.
+    pub fn to(&self) -> NicId {
+        self.to
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    /// The message bytes for the simulator to change, copied first if they are shared.
+    pub(crate) fn data_mut(&mut self) -> &mut Vec<u8> {
+        if let Payload::Shared(data) = &self.data {
+            self.data = Payload::Owned(data.to_vec());
+        }
+        match &mut self.data {
+            Payload::Owned(data) => data,
+            Payload::Shared(_) => unreachable!("shared payloads were just copied"),
+        }
+    }
+
+    /// Messages with a higher priority are received first.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// The id the simulation gave the frame when it was first sent. Ids start from 1,
+    /// so messages the simulation never sent, such as those pieced together by a protocol helper, have 0.
+    pub fn id(&self) -> MsgId {
+        self.id
+    }
+
+    /// How many more times the message may be passed on with `Mailbox::forward`.
+    pub fn ttl(&self) -> u8 {
+        self.ttl
+    }
+
+    /// Whether the data still matches the checksum taken when it was sent,
+    /// so `false` if bits were flipped on the way. Messages without a checksum are always valid.
+    pub fn is_valid(&self) -> bool {
+        self.checksum
+            .is_none_or(|checksum| crc::crc32(self.data()) == checksum)
+    }
+
+    /// The message bytes, copied if they are shared with other receivers of a broadcast.
+    pub fn into_data(self) -> Vec<u8> {
+        match self.data {
+            Payload::Owned(data) => data,
+            Payload::Shared(data) => data.to_vec(),
+        }
+    }
+
+    /// The message bytes, shared without copying if they came from a broadcast or multicast.
+    pub fn into_shared(self) -> Arc<[u8]> {
+        match self.data {
+            Payload::Owned(data) => data.into(),
+            Payload::Shared(data) => data,
+        }
+    }
+}
 
 pub struct RecvMessage<'a> {
     mailbox: &'a mut Mailbox,
@@ -16,31 +201,602 @@ pub struct RecvMessage<'a> {
 impl Future for RecvMessage<'_> {
     type Output = IncomingMsg;
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
-        if self.mailbox.incoming.len() > 0 {
-            Poll::Ready(self.mailbox.incoming.pop().unwrap())
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.mailbox.state();
+        if let Some(msg) = state.pop() {
+            Poll::Ready(msg)
         } else {
-            cx.waker().wake_by_ref();
+            // The simulator wakes the node once a message is delivered, or its budget is renewed.
+            state.waker = Some(cx.waker().clone());
             Poll::Pending
         }
     }
 }
 
-pub struct OutgoingMsg {
-    to: NicId,
-    data: Pin<Vec<u8>>,
+pub struct RecvTimeout<'a> {
+    mailbox: &'a mut Mailbox,
+    timeout: u64,
+    /// Set on the first poll, in virtual time.
+    deadline: Option<u64>,
+}
+
+impl Future for RecvTimeout<'_> {
+    type Output = Option<IncomingMsg>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut state = this.mailbox.state();
+        let deadline = *this.deadline.get_or_insert_with(|| {
+            let deadline = state.now.saturating_add(this.timeout);
+            state.timers.push(deadline);
+            deadline
+        });
+        if let Some(msg) = state.pop() {
+            Poll::Ready(Some(msg))
+        } else if state.now >= deadline && !state.exhausted {
+            Poll::Ready(None)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// The next queued message, borrowed from the mailbox by `Mailbox::peek`.
+pub struct PeekedMsg<'a> {
+    state: MutexGuard<'a, MailboxState>,
+}
+
+impl Deref for PeekedMsg<'_> {
+    type Target = IncomingMsg;
+
+    fn deref(&self) -> &Self::Target {
+        self.state
+            .incoming
+            .front()
+            .expect("peeked message should still be queued")
+    }
+}
+
+pub(crate) struct OutgoingMsg {
+    /// The local nic the message leaves from.
+    pub(crate) from: NicId,
+    pub(crate) data: Payload,
+    pub(crate) priority: u8,
+    /// CRC-32 of `data`.
+    pub(crate) checksum: u32,
+    /// Only delivered if the link partner is in the group.
+    pub(crate) group: Option<MulticastGroup>,
+    /// Set by `send_and_wait`, to be told when the message has left.
+    pub(crate) ticket: Option<u64>,
+    /// Dropped instead of sent once it reaches 0.
+    pub(crate) ttl: u8,
+    /// Set by `send_with_flow`, for links that share their bandwidth between flows.
+    pub(crate) flow: u32,
+    /// Set by `forward` to the id the message already has, otherwise given out once it is sent.
+    pub(crate) id: Option<MsgId>,
+}
+
+#[derive(Default)]
+struct MailboxState {
+    /// Highest priority first, then in order of arrival.
+    incoming: VecDeque<IncomingMsg>,
+    outgoing: Vec<OutgoingMsg>,
+    /// Set when a message is delivered, cleared once the node has been activated for it.
+    unread: bool,
+    waker: Option<Waker>,
+    /// Virtual time, as of the node's last poll.
+    now: u64,
+    /// Times the node has asked to be woken at.
+    timers: Vec<u64>,
+    /// When each local nic on a bandwidth limited link finishes sending what is queued on it.
+    busy_until: HashMap<NicId, u64>,
+    /// Bytes sent from each local nic that have yet to be delivered or dropped.
+    in_flight: HashMap<NicId, usize>,
+    next_ticket: u64,
+    /// When each message sent with `send_and_wait` finishes leaving its nic, by ticket.
+    departures: HashMap<u64, u64>,
+    /// How many more messages the node may take before it has to let other nodes have a turn.
+    budget: Option<usize>,
+    /// Set once the node was held back by its budget with messages still queued.
+    exhausted: bool,
+    /// Whether each local nic can send, for those whose link changed after the node's `Nics` were taken.
+    sendable: HashMap<NicId, bool>,
+}
+
+impl MailboxState {
+    /// Whether `nic` is linked and not receive only, as of now rather than when its `Nics` were taken.
+    fn can_send(&self, nic: &Nic) -> bool {
+        self.sendable
+            .get(&nic.id)
+            .copied()
+            .unwrap_or(nic.link_id.is_some() && !nic.receive_only)
+    }
+
+    /// Take the next incoming message, unless the node has used up its budget.
+    fn pop(&mut self) -> Option<IncomingMsg> {
+        self.pop_at(0)
+    }
+
+    /// Take the incoming message at `position`, unless the node has used up its budget.
+    fn pop_at(&mut self, position: usize) -> Option<IncomingMsg> {
+        if self.held_back() {
+            self.exhausted = true;
+            return None;
+        }
+        let msg = self.incoming.remove(position)?;
+        if let Some(budget) = self.budget.as_mut() {
+            *budget -= 1;
+        }
+        Some(msg)
+    }
+
+    /// Whether messages are queued that the budget keeps the node from taking until its next poll.
+    fn held_back(&self) -> bool {
+        self.budget == Some(0) && !self.incoming.is_empty()
+    }
+
+    fn in_flight_bytes(&self, nic: NicId) -> usize {
+        let queued: usize = self
+            .outgoing
+            .iter()
+            .filter(|msg| msg.from == nic)
+            .map(|msg| msg.data.len())
+            .sum();
+        queued + self.in_flight.get(&nic).copied().unwrap_or(0)
+    }
 }
 
+/// A node's connection to the rest of the simulation.
+///
+/// The simulator holds a handle to the same state, so messages delivered while
+/// a node is awaiting `recv` become visible to it immediately.
+#[derive(Default)]
 pub struct Mailbox {
-    incoming: Vec<IncomingMsg>,
+    state: Arc<Mutex<MailboxState>>,
 }
 
 impl Mailbox {
-    async fn send(out: &Nic) {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Another handle to the same mailbox.
+    pub(crate) fn handle(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, MailboxState> {
+        self.state
+            .lock()
+            .expect("mailbox lock should never be poisoned")
+    }
+
+    /// Send `data` out of `nic` to its link partner.
+    ///
+    /// Fails with `NodeError::LinkDown` if the nic is not linked, even if it was when `nic` was handed out,
+    /// or `NodeError::FrameTooLarge` if `data` is larger than the nic's mtu.
+    pub fn send(&mut self, nic: &Nic, data: Vec<u8>) -> Result<(), NodeError> {
+        self.send_with_priority(nic, data, 0)
+    }
+
+    /// Like `send`, but the receiver gets the message ahead of any queued with a lower `priority`.
+    pub fn send_with_priority(
+        &mut self,
+        nic: &Nic,
+        data: Vec<u8>,
+        priority: u8,
+    ) -> Result<(), NodeError> {
+        let mut state = self.state();
+        if !state.can_send(nic) {
+            return Err(NodeError::LinkDown);
+        }
+        if data.len() > nic.mtu {
+            return Err(NodeError::FrameTooLarge);
+        }
+        state.outgoing.push(OutgoingMsg {
+            from: nic.id,
+            checksum: crc::crc32(&data),
+            data: data.into(),
+            priority,
+            group: None,
+            ticket: None,
+            ttl: DEFAULT_TTL,
+            flow: 0,
+            id: None,
+        });
+        Ok(())
+    }
+
+    /// Like `send`, but drops `data` instead of failing when `nic` can't carry it, as it is unlinked,
+    /// receive only, or `data` is over its mtu. For nodes that pass frames on. Returns whether it was sent.
+    pub fn send_or_drop(&mut self, nic: &Nic, data: Vec<u8>) -> Result<bool, NodeError> {
+        match self.send(nic, data) {
+            Ok(()) => Ok(true),
+            Err(NodeError::LinkDown | NodeError::FrameTooLarge) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Like `send`, but first wait until fewer than `window` bytes sent from `nic` are still in flight.
+    pub async fn send_backpressured(
+        &mut self,
+        nic: &Nic,
+        data: Vec<u8>,
+        window: usize,
+    ) -> Result<(), NodeError> {
+        std::future::poll_fn(|cx| {
+            let mut state = self.state();
+            if state.in_flight_bytes(nic.id) < window {
+                Poll::Ready(())
+            } else {
+                // The simulator wakes the node as messages from it land.
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await;
+        self.send(nic, data)
+    }
+
+    /// Like `send`, but resolves once the frame has fully left `nic`, after the frames queued ahead of it
+    /// on a bandwidth limited link, so `now` is then when its transmission finished.
+    /// Frames the simulator drops instead of sending resolve straight away.
+    pub async fn send_and_wait(&mut self, nic: &Nic, data: Vec<u8>) -> Result<(), NodeError> {
+        self.send(nic, data)?;
+        let ticket = {
+            let mut state = self.state();
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state
+                .outgoing
+                .last_mut()
+                .expect("a message should have just been sent")
+                .ticket = Some(ticket);
+            ticket
+        };
+        std::future::poll_fn(|cx| {
+            let mut state = self.state();
+            match state.departures.get(&ticket) {
+                Some(&at) if at <= state.now => {
+                    state.departures.remove(&ticket);
+                    Poll::Ready(())
+                }
+                // The simulator wakes the node once it has left.
+                _ => {
+                    state.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Bytes sent from the local nic `nic` that have yet to be delivered or dropped,
+    /// including those not yet handed to the link.
+    pub fn in_flight_bytes(&self, nic: NicId) -> usize {
+        self.state().in_flight_bytes(nic)
+    }
+
+    /// Pass `msg` on out of `nic` with one less hop on its TTL and the same `IncomingMsg::id`, failing like `send`.
+    /// Once the TTL runs out the simulator drops it, as `DropReason::TtlExpired`.
+    pub fn forward(&mut self, msg: IncomingMsg, nic: &Nic) -> Result<(), NodeError> {
+        let ttl = msg.ttl.saturating_sub(1);
+        let (priority, id) = (msg.priority, msg.id);
+        self.send_with_priority(nic, msg.into_data(), priority)?;
+        let mut state = self.state();
+        let out = state
+            .outgoing
+            .last_mut()
+            .expect("a message should have just been sent");
+        out.ttl = ttl;
+        // Messages the simulation never sent get an id of their own.
+        out.id = (id != MsgId::default()).then_some(id);
+        Ok(())
+    }
+
+    /// Like `send`, tagging the message as part of `flow`. Links given weights with `Topology::set_flow_weights`
+    /// share their bandwidth between flows by weight; elsewhere the tag is ignored. Untagged messages are flow 0.
+    pub fn send_with_flow(&mut self, nic: &Nic, data: Vec<u8>, flow: u32) -> Result<(), NodeError> {
+        self.send(nic, data)?;
+        self.state()
+            .outgoing
+            .last_mut()
+            .expect("a message should have just been sent")
+            .flow = flow;
+        Ok(())
+    }
+
+    /// Send `data` out of whichever of `nics` is linked to the nic at `dst`, failing like `send`,
+    /// or with `NodeError::Unreachable` if none is.
+    pub fn send_to_mac(
+        &mut self,
+        dst: EthernetAddress,
+        data: Vec<u8>,
+        nics: &Nics<'_>,
+    ) -> Result<(), NodeError> {
+        let nic = nics
+            .iter()
+            .find(|nic| nic.partner_mac == Some(dst))
+            .ok_or(NodeError::Unreachable)?;
+        self.send(nic, data)
+    }
 
+    /// Send `frame` out of `nic`, failing like `send`.
+    pub fn send_frame(&mut self, nic: &Nic, frame: &Frame) -> Result<(), NodeError> {
+        self.send(nic, frame.to_bytes())
     }
 
-    async fn recv() {
+    /// Send `data` out of every linked nic whose mtu allows it.
+    /// Every receiver shares the same bytes; see `IncomingMsg::into_shared`.
+    pub fn broadcast(&mut self, data: impl Into<Arc<[u8]>>, nics: &Nics<'_>) {
+        self.flood(data.into(), nics, None);
+    }
+
+    /// Send `data` to every link partner that has joined `group`, sharing the bytes like `broadcast`.
+    pub fn send_multicast(
+        &mut self,
+        group: MulticastGroup,
+        data: impl Into<Arc<[u8]>>,
+        nics: &Nics<'_>,
+    ) {
+        self.flood(data.into(), nics, Some(group));
+    }
+
+    fn flood(&mut self, data: Arc<[u8]>, nics: &Nics<'_>, group: Option<MulticastGroup>) {
+        let checksum = crc::crc32(&data);
+        let mut state = self.state();
+        let nics: Vec<&Nic> = nics
+            .iter()
+            .filter(|nic| state.can_send(nic) && data.len() <= nic.mtu)
+            .collect();
+        for nic in nics {
+            state.outgoing.push(OutgoingMsg {
+                from: nic.id,
+                data: Payload::Shared(Arc::clone(&data)),
+                priority: 0,
+                checksum,
+                group,
+                ticket: None,
+                ttl: DEFAULT_TTL,
+                flow: 0,
+                id: None,
+            });
+        }
+    }
+
+    /// Virtual time, in nanoseconds. While a message is being handled, the time it was delivered.
+    pub fn now(&self) -> u64 {
+        self.state().now
+    }
+
+    /// The fraction of the next `UTILIZATION_WINDOW` that `nic` will spend sending frames already queued on it,
+    /// from 0.0 when idle to 1.0 when saturated. Always 0.0 on links without a bandwidth limit.
+    pub fn link_utilization(&self, nic: NicId) -> f64 {
+        let state = self.state();
+        let backlog = state
+            .busy_until
+            .get(&nic)
+            .map_or(0, |busy| busy.saturating_sub(state.now));
+        backlog.min(UTILIZATION_WINDOW) as f64 / UTILIZATION_WINDOW as f64
+    }
+
+    /// Wait for the next incoming message.
+    pub fn recv(&mut self) -> RecvMessage<'_> {
+        RecvMessage { mailbox: self }
+    }
+
+    /// Wait for the next incoming message, giving up once `timeout` of virtual time has passed.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> RecvTimeout<'_> {
+        RecvTimeout {
+            mailbox: self,
+            timeout: timeout.as_nanos().try_into().unwrap_or(u64::MAX),
+            deadline: None,
+        }
+    }
+
+    /// Wait for the next incoming ethernet frame, along with the local nic it arrived on.
+    /// Messages too short to be a frame are skipped.
+    pub async fn recv_frame(&mut self) -> (NicId, Frame) {
+        loop {
+            let msg = self.recv().await;
+            if let Some(frame) = Frame::parse(msg.data()) {
+                return (msg.to(), frame);
+            }
+        }
+    }
+
+    /// Take the next incoming message, if one is already queued.
+    pub fn try_recv(&mut self) -> Option<IncomingMsg> {
+        self.state().pop()
+    }
+
+    /// Look at the next message `try_recv` would return, leaving it queued.
+    ///
+    /// The mailbox is locked while the message is borrowed, hence `&mut self`.
+    pub fn peek(&mut self) -> Option<PeekedMsg<'_>> {
+        let state = self.state();
+        (!state.incoming.is_empty()).then_some(PeekedMsg { state })
+    }
+
+    /// Take every queued message at once, in the order `try_recv` would return them.
+    pub fn drain(&mut self) -> Vec<IncomingMsg> {
+        let mut state = self.state();
+        std::iter::from_fn(|| state.pop()).collect()
+    }
 
+    /// Take the next queued message that arrived on the local nic `nic`.
+    pub fn try_recv_on(&mut self, nic: NicId) -> Option<IncomingMsg> {
+        let mut state = self.state();
+        let position = state.incoming.iter().position(|msg| msg.to == nic)?;
+        state.pop_at(position)
     }
-}
\ No newline at end of file
+
+    /// Queue a message for the node and wake it if it is waiting on `recv`.
+    pub(crate) fn deliver(&self, msg: IncomingMsg) {
+        let mut state = self.state();
+        // Behind every queued message of the same or higher priority.
+        let position = state
+            .incoming
+            .iter()
+            .position(|queued| queued.priority < msg.priority)
+            .unwrap_or(state.incoming.len());
+        state.incoming.insert(position, msg);
+        state.unread = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Wake the node if it is waiting on the mailbox.
+    pub(crate) fn wake(&self) {
+        if let Some(waker) = self.state().waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Count `bytes` sent from `nic` as in flight.
+    pub(crate) fn launched(&self, nic: NicId, bytes: usize) {
+        *self.state().in_flight.entry(nic).or_default() += bytes;
+    }
+
+    /// Stop counting `bytes` sent from `nic` as in flight, waking the node in case it is waiting on them.
+    pub(crate) fn landed(&self, nic: NicId, bytes: usize) {
+        if let Some(in_flight) = self.state().in_flight.get_mut(&nic) {
+            *in_flight = in_flight.saturating_sub(bytes);
+        }
+        self.wake();
+    }
+
+    /// Tell the node whether `nic` can send, after its link went up or down while the node was running.
+    pub(crate) fn set_sendable(&self, nic: NicId, sendable: bool) {
+        self.state().sendable.insert(nic, sendable);
+    }
+
+    pub(crate) fn set_now(&self, now: u64) {
+        self.state().now = now;
+    }
+
+    /// Queue `duration` of sending on `nic`, starting no earlier than `now`, and return when it finishes.
+    pub(crate) fn occupy(&self, nic: NicId, now: u64, duration: u64) -> u64 {
+        let mut state = self.state();
+        let busy = state.busy_until.entry(nic).or_default();
+        *busy = (*busy).max(now) + duration;
+        *busy
+    }
+
+    /// Tell `send_and_wait` its message finishes leaving at `at`, waking the node then.
+    pub(crate) fn departs(&self, ticket: u64, at: u64) {
+        let mut state = self.state();
+        state.departures.insert(ticket, at);
+        state.timers.push(at);
+    }
+
+    /// Take back a departure told with `departs`, before the node has seen it.
+    pub(crate) fn withdraw_departure(&self, ticket: u64) {
+        self.state().departures.remove(&ticket);
+    }
+
+    /// Times the node has asked to be woken at since the last call.
+    pub(crate) fn take_timers(&self) -> Vec<u64> {
+        std::mem::take(&mut self.state().timers)
+    }
+
+    /// Messages sent by the node since the last call.
+    pub(crate) fn take_outgoing(&self) -> Vec<OutgoingMsg> {
+        std::mem::take(&mut self.state().outgoing)
+    }
+
+    /// Let the node take up to `budget` messages before it is next polled, or any number with `None`.
+    pub(crate) fn set_budget(&self, budget: Option<usize>) {
+        let mut state = self.state();
+        state.budget = budget;
+        state.exhausted = false;
+    }
+
+    /// Whether the node was held back by its budget since it was set, leaving messages for its next poll.
+    pub(crate) fn take_exhausted(&self) -> bool {
+        let mut state = self.state();
+        let exhausted = std::mem::take(&mut state.exhausted);
+        if exhausted {
+            state.unread = true;
+        }
+        exhausted
+    }
+
+    /// Whether messages have arrived since the node was last activated, and are still queued.
+    pub(crate) fn take_unread(&self) -> bool {
+        let mut state = self.state();
+        let unread = state.unread && !state.incoming.is_empty();
+        state.unread = false;
+        unread
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_recv_drains_in_order() {
+        let mut mailbox = Mailbox::new();
+        assert!(mailbox.try_recv().is_none());
+
+        for i in 0..3 {
+            mailbox.deliver(IncomingMsg::new(i, 9, vec![i as u8]));
+        }
+        let mut drained = Vec::new();
+        while let Some(msg) = mailbox.try_recv() {
+            drained.push(msg.into_data());
+        }
+        assert_eq!(drained, vec![vec![0], vec![1], vec![2]]);
+        assert!(mailbox.try_recv().is_none());
+    }
+
+    #[test]
+    fn peek_leaves_message_queued() {
+        let mut mailbox = Mailbox::new();
+        assert!(mailbox.peek().is_none());
+        mailbox.deliver(IncomingMsg::new(4, 9, b"hello".to_vec()));
+        let peeked = mailbox.peek().expect("A message is queued");
+        assert_eq!(peeked.from(), 4);
+        assert_eq!(peeked.data().len(), 5);
+        drop(peeked);
+        let msg = mailbox.try_recv().expect("Peeking leaves the message");
+        assert_eq!(msg.into_data(), b"hello");
+        assert!(mailbox.peek().is_none());
+    }
+
+    #[test]
+    fn drain_takes_everything_in_order() {
+        let mut mailbox = Mailbox::new();
+        for i in 0..5 {
+            mailbox.deliver(IncomingMsg::new(i, 9, vec![i as u8]));
+        }
+        let drained: Vec<_> = mailbox
+            .drain()
+            .into_iter()
+            .map(IncomingMsg::into_data)
+            .collect();
+        assert_eq!(drained, [[0], [1], [2], [3], [4]]);
+        assert!(mailbox.drain().is_empty());
+        assert!(mailbox.try_recv().is_none());
+    }
+
+    #[test]
+    fn higher_priority_received_first() {
+        let mut mailbox = Mailbox::new();
+        mailbox.deliver(IncomingMsg::new(0, 9, b"low".to_vec()));
+        mailbox.deliver(IncomingMsg::new(0, 9, b"high".to_vec()).with_priority(7));
+        mailbox.deliver(IncomingMsg::new(0, 9, b"low again".to_vec()));
+        mailbox.deliver(IncomingMsg::new(0, 9, b"high again".to_vec()).with_priority(7));
+
+        let drained: Vec<_> = std::iter::from_fn(|| mailbox.try_recv())
+            .map(IncomingMsg::into_data)
+            .collect();
+        assert_eq!(drained, [&b"high"[..], b"high again", b"low", b"low again"]);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::message::{IncomingMsg, Mailbox};
+use crate::nics::{Nic, Nics};
+use crate::node::NodeError;
+
+/// A kind byte, then the sequence number as a big endian `u32`.
+const HEADER_LEN: usize = 5;
+const DATA: u8 = 0;
+const ACK: u8 = 1;
+
+/// Why `AckTracker::send_reliable` failed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AckError {
+    /// The message could not be sent at all.
+    Send(NodeError),
+    /// The timeout passed, in virtual time, without an acknowledgement.
+    Timeout,
+}
+
+impl From<NodeError> for AckError {
+    fn from(error: NodeError) -> Self {
+        AckError::Send(error)
+    }
+}
+
+/// Numbers outgoing messages and waits for the receiver to acknowledge them.
+///
+/// Keep one per node across calls to `process`, and use it in place of `Mailbox::send` and
+/// `Mailbox::recv`. Both ends of a link must use one, since every frame carries a sequence number.
+pub struct AckTracker {
+    next_seq: u32,
+    /// How long `send_reliable` waits, in nanoseconds of virtual time.
+    timeout: u64,
+    /// Acknowledge data as it is received.
+    auto_ack: bool,
+    /// Data received while waiting for an acknowledgement, with its header removed.
+    received: VecDeque<IncomingMsg>,
+}
+
+impl AckTracker {
+    /// `send_reliable` gives up once `timeout` has passed. Received data is acknowledged automatically.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            next_seq: 0,
+            timeout: timeout.as_nanos().try_into().unwrap_or(u64::MAX),
+            auto_ack: true,
+            received: VecDeque::new(),
+        }
+    }
+
+    /// Whether received data is acknowledged, so a sender's `send_reliable` can resolve.
+    pub fn auto_ack(mut self, auto_ack: bool) -> Self {
+        self.auto_ack = auto_ack;
+        self
+    }
+
+    /// Send `data` out of `nic`, then wait for the receiver to acknowledge it.
+    ///
+    /// Data that arrives in the meantime is kept for `recv`. `nics` are the node's nics,
+    /// which that data is acknowledged through.
+    pub async fn send_reliable(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+        nic: &Nic,
+        data: Vec<u8>,
+    ) -> Result<(), AckError> {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        mail.send(nic, frame(DATA, seq, &data))?;
+
+        let deadline = mail.now().saturating_add(self.timeout);
+        loop {
+            let remaining = deadline.saturating_sub(mail.now());
+            let Some(msg) = mail.recv_timeout(Duration::from_nanos(remaining)).await else {
+                return Err(AckError::Timeout);
+            };
+            match parse(&msg) {
+                Some((ACK, acked)) if acked == seq && msg.to() == nic.id => return Ok(()),
+                Some((DATA, _)) => self.accept(mail, nics, msg)?,
+                _ => {}
+            }
+        }
+    }
+
+    /// Wait for the next data message, acknowledging it if `auto_ack` is set.
+    /// Stray acknowledgements and frames without a header are skipped.
+    pub async fn recv(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<IncomingMsg, NodeError> {
+        while self.received.is_empty() {
+            let msg = mail.recv().await;
+            if let Some((DATA, _)) = parse(&msg) {
+                self.accept(mail, nics, msg)?;
+            }
+        }
+        Ok(self
+            .received
+            .pop_front()
+            .expect("a message should have been received"))
+    }
+
+    /// Keep a data message, and acknowledge it if `auto_ack` is set.
+    fn accept(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+        msg: IncomingMsg,
+    ) -> Result<(), NodeError> {
+        let (_, seq) = parse(&msg).expect("the message should have a header");
+        if self.auto_ack {
+            if let Some(nic) = nics.find_id(msg.to()) {
+                mail.send(nic, frame(ACK, seq, &[]))?;
+            }
+        }
+        let (from, to, id) = (msg.from(), msg.to(), msg.id());
+        let data = msg.into_data().split_off(HEADER_LEN);
+        self.received
+            .push_back(IncomingMsg::new(from, to, data).with_id(id));
+        Ok(())
+    }
+}
+
+fn frame(kind: u8, seq: u32, data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + data.len());
+    frame.push(kind);
+    frame.extend_from_slice(&seq.to_be_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// The kind and sequence number of a frame, if it has a header.
+fn parse(msg: &IncomingMsg) -> Option<(u8, u32)> {
+    let data = msg.data();
+    let header = data.get(..HEADER_LEN)?;
+    Some((header[0], u32::from_be_bytes(header[1..].try_into().ok()?)))
+}
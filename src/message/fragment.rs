@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::message::{IncomingMsg, Mailbox};
+use crate::nics::{Nic, NicId};
+use crate::node::NodeError;
+
+/// Fragment id, offset and total length, each a big endian `u32`.
+const HEADER_LEN: usize = 12;
+
+/// A message being put back together.
+struct Partial {
+    to: NicId,
+    total: usize,
+    fragments: BTreeMap<u32, Vec<u8>>,
+    /// When the first fragment arrived, in virtual time.
+    started: u64,
+}
+
+impl Partial {
+    fn received(&self) -> usize {
+        self.fragments.values().map(Vec::len).sum()
+    }
+}
+
+/// Splits messages larger than a nic's mtu into fragments, and reassembles them on receipt.
+///
+/// Keep one per node across calls to `process`, and use it in place of `Mailbox::send` and
+/// `Mailbox::recv`. Both ends of a link must use one, since every frame carries a fragment header.
+pub struct FragmentingMailbox {
+    next_id: u32,
+    /// Fragments of messages not yet complete, by the nic that sent them and their id.
+    partial: HashMap<(NicId, u32), Partial>,
+    /// How long an incomplete message is kept, in nanoseconds of virtual time.
+    timeout: u64,
+}
+
+impl FragmentingMailbox {
+    /// Incomplete messages are dropped once `timeout` has passed since their first fragment arrived.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            next_id: 0,
+            partial: HashMap::new(),
+            timeout: timeout.as_nanos().try_into().unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Send `data` out of `nic`, in as many fragments as its mtu requires.
+    ///
+    /// Fails with `NodeError::FrameTooLarge` if the mtu can't fit a fragment header and at least
+    /// one byte, or the message is too long to describe in the header.
+    pub fn send(&mut self, mail: &mut Mailbox, nic: &Nic, data: Vec<u8>) -> Result<(), NodeError> {
+        let chunk = nic.mtu.saturating_sub(HEADER_LEN);
+        let total = u32::try_from(data.len()).map_err(|_| NodeError::FrameTooLarge)?;
+        if chunk == 0 {
+            return Err(NodeError::FrameTooLarge);
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        // An empty message is still sent, as a single empty fragment.
+        let chunks: Vec<&[u8]> = if data.is_empty() {
+            vec![&[]]
+        } else {
+            data.chunks(chunk).collect()
+        };
+        for (i, fragment) in chunks.into_iter().enumerate() {
+            let offset = (i * chunk) as u32;
+            let mut frame = Vec::with_capacity(HEADER_LEN + fragment.len());
+            frame.extend_from_slice(&id.to_be_bytes());
+            frame.extend_from_slice(&offset.to_be_bytes());
+            frame.extend_from_slice(&total.to_be_bytes());
+            frame.extend_from_slice(fragment);
+            mail.send(nic, frame)?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the next complete message.
+    pub async fn recv(&mut self, mail: &mut Mailbox) -> IncomingMsg {
+        loop {
+            let msg = mail.recv().await;
+            let now = mail.now();
+            if let Some(complete) = self.accept(msg, now) {
+                return complete;
+            }
+        }
+    }
+
+    /// Take in a fragment received at `now`, returning the message it completes, if any.
+    /// Frames too short to carry a fragment header are dropped.
+    pub(crate) fn accept(&mut self, msg: IncomingMsg, now: u64) -> Option<IncomingMsg> {
+        let timeout = self.timeout;
+        self.partial
+            .retain(|_, partial| now.saturating_sub(partial.started) < timeout);
+
+        let data = msg.data();
+        if data.len() < HEADER_LEN {
+            return None;
+        }
+        let field = |at: usize| u32::from_be_bytes(data[at..at + 4].try_into().unwrap());
+        let (id, offset, total) = (field(0), field(4), field(8));
+
+        let key = (msg.from(), id);
+        let partial = self.partial.entry(key).or_insert_with(|| Partial {
+            to: msg.to(),
+            total: total as usize,
+            fragments: BTreeMap::new(),
+            started: now,
+        });
+        partial
+            .fragments
+            .insert(offset, data[HEADER_LEN..].to_vec());
+        if partial.received() < partial.total {
+            return None;
+        }
+
+        let partial = self.partial.remove(&key)?;
+        let data: Vec<u8> = partial.fragments.into_values().flatten().collect();
+        Some(IncomingMsg::new(key.0, partial.to, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(id: u32, offset: u32, total: u32, data: &[u8]) -> IncomingMsg {
+        let mut frame = Vec::new();
+        for field in [id, offset, total] {
+            frame.extend_from_slice(&field.to_be_bytes());
+        }
+        frame.extend_from_slice(data);
+        IncomingMsg::new(7, 8, frame)
+    }
+
+    #[test]
+    fn reassembles_out_of_order() {
+        let mut mailbox = FragmentingMailbox::new(Duration::from_nanos(100));
+        assert!(mailbox.accept(fragment(0, 4, 6, b"ef"), 0).is_none());
+        let msg = mailbox
+            .accept(fragment(0, 0, 6, b"abcd"), 10)
+            .expect("Message is complete");
+        assert_eq!(msg.data(), b"abcdef");
+        assert_eq!((msg.from(), msg.to()), (7, 8));
+    }
+
+    #[test]
+    fn drops_incomplete_after_timeout() {
+        let mut mailbox = FragmentingMailbox::new(Duration::from_nanos(100));
+        assert!(mailbox.accept(fragment(0, 0, 6, b"abcd"), 0).is_none());
+        // The first fragment has expired, so this one can't complete the message.
+        assert!(mailbox.accept(fragment(0, 4, 6, b"ef"), 100).is_none());
+        assert!(mailbox.accept(fragment(1, 0, 2, b"ok"), 150).is_some());
+    }
+}
@@ -0,0 +1,64 @@
+use smoltcp::wire::{EthernetAddress, EthernetFrame, EthernetProtocol, ETHERNET_HEADER_LEN};
+
+/// An ethernet frame, for nodes that would rather not lay out the header themselves.
+/// Sent with `Mailbox::send_frame`, and received with `Mailbox::recv_frame`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub src_mac: EthernetAddress,
+    pub dst_mac: EthernetAddress,
+    pub ethertype: EthernetProtocol,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(
+        src_mac: EthernetAddress,
+        dst_mac: EthernetAddress,
+        ethertype: EthernetProtocol,
+        payload: Vec<u8>,
+    ) -> Self {
+        Self {
+            src_mac,
+            dst_mac,
+            ethertype,
+            payload,
+        }
+    }
+
+    /// The frame's length on the wire, header included.
+    pub fn wire_len(&self) -> usize {
+        ETHERNET_HEADER_LEN + self.payload.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut frame = EthernetFrame::new_unchecked(vec![0; self.wire_len()]);
+        frame.set_dst_addr(self.dst_mac);
+        frame.set_src_addr(self.src_mac);
+        frame.set_ethertype(self.ethertype);
+        frame.payload_mut().copy_from_slice(&self.payload);
+        frame.into_inner()
+    }
+
+    /// Returns `None` if `data` is too short to hold an ethernet header.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let frame = EthernetFrame::new_checked(data).ok()?;
+        Some(Self {
+            src_mac: frame.src_addr(),
+            dst_mac: frame.dst_addr(),
+            ethertype: frame.ethertype(),
+            payload: frame.payload().to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_too_short() {
+        assert_eq!(Frame::parse(&[0; ETHERNET_HEADER_LEN - 1]), None);
+        let empty = Frame::parse(&[0; ETHERNET_HEADER_LEN]).expect("Header fits");
+        assert!(empty.payload.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::message::{IncomingMsg, Mailbox};
+use crate::nics::{Nic, NicId};
+use crate::node::NodeError;
+
+/// Sequence number, a big endian `u32`.
+const HEADER_LEN: usize = 4;
+
+/// Frames from one sender that arrived ahead of a missing one.
+#[derive(Default)]
+struct Stream {
+    /// The sequence number expected next.
+    next: u32,
+    held: BTreeMap<u32, IncomingMsg>,
+    /// When the stream started waiting for the frame expected next, in virtual time.
+    waiting_since: Option<u64>,
+    /// With `dedup_on_receive`, the sequence numbers recently handed over.
+    seen: BTreeSet<u32>,
+    /// With `dedup_on_receive`, the furthest sequence number handed over.
+    highest: Option<u32>,
+}
+
+impl Stream {
+    /// Move every held frame that is next in sequence to `ready`, as of `now`.
+    fn release(&mut self, ready: &mut VecDeque<IncomingMsg>, now: u64) {
+        let before = self.next;
+        while let Some(msg) = self.held.remove(&self.next) {
+            ready.push_back(msg);
+            self.next = self.next.wrapping_add(1);
+        }
+        if self.held.is_empty() {
+            self.waiting_since = None;
+        } else if self.next != before || self.waiting_since.is_none() {
+            self.waiting_since = Some(now);
+        }
+    }
+
+    /// Whether `seq` is new within the last `window` sequence numbers, remembering it if so.
+    fn first_sighting(&mut self, seq: u32, window: u32) -> bool {
+        let highest = *self.highest.get_or_insert(seq);
+        let behind = highest.wrapping_sub(seq);
+        if behind <= u32::MAX / 2 && (behind >= window || self.seen.contains(&seq)) {
+            return false;
+        }
+        if behind > u32::MAX / 2 {
+            self.highest = Some(seq);
+        }
+        self.seen.insert(seq);
+        let highest = self.highest.unwrap_or(seq);
+        self.seen
+            .retain(|&seen| highest.wrapping_sub(seen) < window);
+        true
+    }
+}
+
+/// Numbers each frame sent, and hands received frames over in the order they were sent.
+///
+/// Frames that arrive early are held until the ones before them arrive, or until `timeout`
+/// passes and the missing frames are given up on. Frames arriving after that are dropped.
+/// Keep one per node across calls to `process`, and use it in place of `Mailbox::send` and
+/// `Mailbox::recv`. Both ends of a link must use one, since every frame carries a sequence number.
+pub struct SequencedChannel {
+    /// The next sequence number to send, by local nic.
+    next_seq: HashMap<NicId, u32>,
+    /// Received frames, by the nic that sent them.
+    streams: HashMap<NicId, Stream>,
+    ready: VecDeque<IncomingMsg>,
+    /// How long early frames wait for a missing one, in nanoseconds of virtual time.
+    timeout: u64,
+    /// Set by `dedup_on_receive`.
+    dedup_window: Option<u32>,
+}
+
+impl SequencedChannel {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            next_seq: HashMap::new(),
+            streams: HashMap::new(),
+            ready: VecDeque::new(),
+            timeout: timeout.as_nanos().try_into().unwrap_or(u64::MAX),
+            dedup_window: None,
+        }
+    }
+
+    /// Hand frames over as soon as they arrive instead of in order, only dropping duplicates:
+    /// frames whose sequence number was already seen, or is more than `window` behind the furthest seen,
+    /// from the same sender. `None` goes back to reordering.
+    ///
+    /// # Panics!
+    /// If `window` is `Some(0)`, which would drop every frame.
+    pub fn dedup_on_receive(mut self, window: Option<u32>) -> Self {
+        assert!(window != Some(0), "dedup window should be at least 1");
+        self.dedup_window = window;
+        self
+    }
+
+    /// Send `data` out of `nic`, numbered after the last frame sent from it.
+    ///
+    /// Fails like `Mailbox::send`, counting the sequence number's bytes against the mtu.
+    pub fn send(&mut self, mail: &mut Mailbox, nic: &Nic, data: Vec<u8>) -> Result<(), NodeError> {
+        let seq = self.next_seq.entry(nic.id).or_default();
+        let mut frame = Vec::with_capacity(HEADER_LEN + data.len());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&data);
+        mail.send(nic, frame)?;
+        *seq = seq.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Wait for the next frame in sequence.
+    pub async fn recv(&mut self, mail: &mut Mailbox) -> IncomingMsg {
+        loop {
+            if let Some(msg) = self.ready.pop_front() {
+                return msg;
+            }
+            let deadline = self
+                .streams
+                .values()
+                .filter_map(|stream| stream.waiting_since)
+                .min()
+                .map(|since| since.saturating_add(self.timeout));
+            let msg = match deadline {
+                Some(deadline) => {
+                    let wait = deadline.saturating_sub(mail.now());
+                    mail.recv_timeout(Duration::from_nanos(wait)).await
+                }
+                None => Some(mail.recv().await),
+            };
+            let now = mail.now();
+            match msg {
+                Some(msg) => self.accept(msg, now),
+                None => self.expire(now),
+            }
+        }
+    }
+
+    /// Take in a frame received at `now`, readying it and any held behind it if it is next.
+    /// Frames too short to carry a sequence number, or already passed over, are dropped.
+    pub(crate) fn accept(&mut self, msg: IncomingMsg, now: u64) {
+        self.expire(now);
+        let data = msg.data();
+        if data.len() < HEADER_LEN {
+            return;
+        }
+        let seq = u32::from_be_bytes(data[..HEADER_LEN].try_into().unwrap());
+        let stream = self.streams.entry(msg.from()).or_default();
+        if let Some(window) = self.dedup_window {
+            if stream.first_sighting(seq, window) {
+                let unwrapped = IncomingMsg::new(msg.from(), msg.to(), data[HEADER_LEN..].to_vec())
+                    .with_id(msg.id());
+                self.ready.push_back(unwrapped);
+            }
+            return;
+        }
+        // Wrapping distance, so an old frame looks like it is behind rather than far ahead.
+        if seq.wrapping_sub(stream.next) > u32::MAX / 2 {
+            return;
+        }
+        let unwrapped =
+            IncomingMsg::new(msg.from(), msg.to(), data[HEADER_LEN..].to_vec()).with_id(msg.id());
+        stream.held.insert(seq, unwrapped);
+        stream.release(&mut self.ready, now);
+    }
+
+    /// Give up on missing frames that held ones have waited `timeout` for.
+    fn expire(&mut self, now: u64) {
+        for stream in self.streams.values_mut() {
+            let expired = stream
+                .waiting_since
+                .is_some_and(|since| now.saturating_sub(since) >= self.timeout);
+            if expired {
+                if let Some(&first) = stream.held.keys().next() {
+                    stream.next = first;
+                }
+                stream.release(&mut self.ready, now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(seq: u32, data: &[u8]) -> IncomingMsg {
+        let mut frame = seq.to_be_bytes().to_vec();
+        frame.extend_from_slice(data);
+        IncomingMsg::new(7, 8, frame)
+    }
+
+    fn ready(channel: &mut SequencedChannel) -> Vec<Vec<u8>> {
+        channel
+            .ready
+            .drain(..)
+            .map(IncomingMsg::into_data)
+            .collect()
+    }
+
+    #[test]
+    fn reorders_frames() {
+        let mut channel = SequencedChannel::new(Duration::from_nanos(100));
+        channel.accept(frame(2, b"c"), 0);
+        channel.accept(frame(1, b"b"), 10);
+        assert!(ready(&mut channel).is_empty());
+        channel.accept(frame(0, b"a"), 20);
+        assert_eq!(ready(&mut channel), [b"a", b"b", b"c"]);
+    }
+
+    #[test]
+    fn dedup_drops_repeats_within_window() {
+        let mut channel =
+            SequencedChannel::new(Duration::from_nanos(100)).dedup_on_receive(Some(4));
+        for seq in [1, 0, 1, 2, 0, 6, 2, 5] {
+            channel.accept(frame(seq, &[seq as u8]), 0);
+        }
+        // 2 is already too far behind 6 to tell apart from a duplicate.
+        assert_eq!(ready(&mut channel), [[1], [0], [2], [6], [5]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "dedup window should be at least 1")]
+    fn dedup_rejects_empty_window() {
+        let _ = SequencedChannel::new(Duration::from_nanos(100)).dedup_on_receive(Some(0));
+    }
+
+    #[test]
+    fn dedup_window_of_one_drops_only_repeats() {
+        let mut channel =
+            SequencedChannel::new(Duration::from_nanos(100)).dedup_on_receive(Some(1));
+        for seq in [0, 0, 1, 1, 2] {
+            channel.accept(frame(seq, &[seq as u8]), 0);
+        }
+        assert_eq!(ready(&mut channel), [[0], [1], [2]]);
+    }
+
+    #[test]
+    fn skips_missing_frame_after_timeout() {
+        let mut channel = SequencedChannel::new(Duration::from_nanos(100));
+        channel.accept(frame(1, b"b"), 0);
+        channel.expire(99);
+        assert!(ready(&mut channel).is_empty());
+        channel.expire(100);
+        assert_eq!(ready(&mut channel), [b"b"]);
+        // Too late to be delivered.
+        channel.accept(frame(0, b"a"), 150);
+        assert!(ready(&mut channel).is_empty());
+    }
+}
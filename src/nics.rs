@@ -1,48 +1,277 @@
-use smoltcp::wire::EthernetAddress;
-use std::{
-    collections::HashMap,
-    marker::PhantomData,
-    ops::{Index, IndexMut},
-};
+use serde::{Deserialize, Serialize};
+use smoltcp::wire::{EthernetAddress, Ipv4Address, Ipv4Cidr};
+use std::any::Any;
+use std::collections::HashSet;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
 
-use crate::simulator::{SimErr, Topology};
+use crate::node::Node;
+use crate::rng::Rng;
+use crate::simulator::{LinkProfile, SimErr, Topology};
+
+pub mod device;
 
 pub type NicId = u64;
 pub type NicGroup = u64;
 pub type LinkId = u64;
 
-#[derive(PartialEq, Eq, Debug)]
+/// A set of nics that receive the messages sent to it with `Mailbox::send_multicast`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct MulticastGroup(pub u32);
+
+/// The default `Nic::mtu`: an ethernet frame, header included.
+pub const DEFAULT_MTU: usize = 1514;
+
+fn default_mtu() -> usize {
+    DEFAULT_MTU
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_cost() -> u32 {
+    1
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Nic {
     pub(crate) id: NicId,
     /// The node the Nic is accociated with
     pub(crate) group: NicGroup,
 
+    #[serde(with = "ethernet_address")]
     pub(crate) mac: EthernetAddress,
     pub(crate) latency: Option<u64>,
+    /// The largest message, in bytes, the nic can send.
+    #[serde(default = "default_mtu")]
+    pub(crate) mtu: usize,
+    /// A disabled nic keeps its link, but drops every frame sent from or to it.
+    #[serde(default = "default_enabled")]
+    pub(crate) enabled: bool,
+    /// A name for the nic, unique within its node.
+    #[serde(default)]
+    pub(crate) label: Option<String>,
+    /// The weight `Topology::shortest_path_by_cost` gives sending out of the nic, 1 unless set otherwise.
+    #[serde(default = "default_cost")]
+    pub(crate) cost: u32,
+    /// The nic's IPv4 address and the subnet it is on.
+    #[serde(default, with = "ipv4_cidr")]
+    pub(crate) ip: Option<Ipv4Cidr>,
 
     // A link id will be generated when two nodes connect. The value will be shared across both NICs.
     pub(crate) link_id: Option<LinkId>,
+    /// The nic on the other end of the link.
+    #[serde(default)]
+    pub(crate) partner: Option<NicId>,
+    /// The MAC of the nic on the other end of the link.
+    #[serde(default, with = "optional_ethernet_address")]
+    pub(crate) partner_mac: Option<EthernetAddress>,
+    /// The node owning the nic on the other end of the link.
+    #[serde(default)]
+    pub(crate) partner_node: Option<NicGroup>,
+    /// The nic is the far end of a simplex link, so it can only receive over it.
+    #[serde(default)]
+    pub(crate) receive_only: bool,
+    #[serde(skip)]
+    pub(crate) metadata: Metadata,
 }
 
 impl Nic {
-    pub(crate) fn link(&mut self, id: LinkId) {
+    pub fn mac(&self) -> EthernetAddress {
+        self.mac
+    }
+
+    /// The index of the node the nic belongs to.
+    pub fn node(&self) -> NicGroup {
+        self.group
+    }
+
+    /// What sending out of the nic costs, set with `NicAllocator::nic_with_cost` or `NicsMut::set_cost`.
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    /// The value attached with `NicAllocator::nic_with_metadata`, if it is a `T`.
+    pub fn metadata<T: Any>(&self) -> Option<&T> {
+        self.metadata.0.as_deref()?.downcast_ref()
+    }
+
+    /// The id of the nic's link, if it is linked, for the `Topology` setters that take one.
+    pub fn link_id(&self) -> Option<LinkId> {
+        self.link_id
+    }
+
+    /// The MAC of the nic on the other end of the link, if linked.
+    pub fn partner_mac(&self) -> Option<EthernetAddress> {
+        self.partner_mac
+    }
+
+    /// The node owning the nic on the other end of the link, if linked.
+    /// Tells which node sent a message received on the nic.
+    pub fn partner_node(&self) -> Option<NicGroup> {
+        self.partner_node
+    }
+
+    pub(crate) fn link(&mut self, id: LinkId, partner: &Nic) {
         self.link_id = Some(id);
+        self.partner = Some(partner.id);
+        self.partner_mac = Some(partner.mac);
+        self.partner_node = Some(partner.group);
+    }
+
+    pub(crate) fn unlink(&mut self) {
+        self.link_id = None;
+        self.partner = None;
+        self.partner_mac = None;
+        self.partner_node = None;
+        self.receive_only = false;
+    }
+}
+
+/// 64-bit FNV-1a, a hash that is stable across runs and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// A value of any type attached to a nic. Not serialized, and only equal to clones of itself.
+#[derive(Clone, Default)]
+pub(crate) struct Metadata(Option<Arc<dyn Any + Send + Sync>>);
+
+impl PartialEq for Metadata {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for Metadata {}
+
+impl std::fmt::Debug for Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Metadata(..)"),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+/// Serde shim for `EthernetAddress`, stored as its six octets.
+mod ethernet_address {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use smoltcp::wire::EthernetAddress;
+
+    pub(super) fn serialize<S: Serializer>(
+        mac: &EthernetAddress,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        mac.0.serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<EthernetAddress, D::Error> {
+        <[u8; 6]>::deserialize(deserializer).map(EthernetAddress)
+    }
+}
+
+/// Serde shim for an optional `EthernetAddress`, stored as its six octets.
+mod optional_ethernet_address {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use smoltcp::wire::EthernetAddress;
+
+    pub(super) fn serialize<S: Serializer>(
+        mac: &Option<EthernetAddress>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        mac.map(|mac| mac.0).serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<EthernetAddress>, D::Error> {
+        Option::<[u8; 6]>::deserialize(deserializer).map(|mac| mac.map(EthernetAddress))
+    }
+}
+
+/// Serde shim for an optional `Ipv4Cidr`, stored as its four octets and prefix length.
+mod ipv4_cidr {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use smoltcp::wire::{Ipv4Address, Ipv4Cidr};
+
+    pub(super) fn serialize<S: Serializer>(
+        cidr: &Option<Ipv4Cidr>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        cidr.map(|cidr| (cidr.address().octets(), cidr.prefix_len()))
+            .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Ipv4Cidr>, D::Error> {
+        let cidr = Option::<([u8; 4], u8)>::deserialize(deserializer)?;
+        Ok(cidr.map(|(octets, prefix_len)| Ipv4Cidr::new(Ipv4Address::from(octets), prefix_len)))
     }
 }
 
 #[derive(Debug)]
 pub enum NicError {
     NeighborNotFound,
+    /// The nic is not part of any link.
+    NotLinked,
+    /// Every nic of the node is already part of a link.
+    NoFreeNic,
+    /// The nic is already part of a link.
+    AlreadyLinked,
+    /// The nic was asked to link to itself.
+    SelfLink,
 }
 
 // Instead of having a Nics struct, perhaps return a slice of nics vec
 pub struct Nics<'a> {
     nics: &'a [Nic],
+    name: &'a str,
 }
 
 impl<'a> Nics<'a> {
     pub(crate) fn from_slice(nics: &'a [Nic]) -> Self {
-        Self { nics }
+        Self { nics, name: "" }
+    }
+
+    /// Nics of the node called `name`.
+    pub(crate) fn named(mut self, name: &'a str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// The index of the node these nics belong to, unique within the simulation.
+    ///
+    /// # Panics!
+    /// If there are no nics, which is never the case for a node in a simulation.
+    pub fn node_id(&self) -> usize {
+        self.nics[0].group as usize
+    }
+
+    /// The `Node::name` of the node these nics belong to.
+    pub fn node_name(&self) -> &str {
+        self.name
+    }
+
+    /// Returns the nic with the given id, if it belongs to this node.
+    pub fn find_id(&self, id: NicId) -> Option<&Nic> {
+        self.nics.iter().find(|nic| nic.id == id)
+    }
+
+    /// Returns the nic given `label` by `NicAllocator::nic_named`, if found.
+    pub fn find_label(&self, label: &str) -> Option<&Nic> {
+        self.nics
+            .iter()
+            .find(|nic| nic.label.as_deref() == Some(label))
     }
 
     /// Returns a nic with the associated mac address, if found.
@@ -50,9 +279,56 @@ impl<'a> Nics<'a> {
         self.nics.iter().find(|nic| nic.mac == *mac)
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Nic> {
+        self.nics.iter()
+    }
+
+    /// The MAC of each of the node's nics, in order.
+    pub fn macs(&self) -> impl Iterator<Item = EthernetAddress> + '_ {
+        self.nics.iter().map(Nic::mac)
+    }
+
+    /// The node's linked nics, each with the id of the nic on the other end of its link.
+    pub fn linked_nics(&self) -> impl Iterator<Item = (&Nic, NicId)> {
+        self.nics.iter().filter_map(|nic| Some((nic, nic.partner?)))
+    }
+
+    /// The linked nic to send a packet for `dst` out of, among those whose subnets most specifically
+    /// contain it, spreading flows over them by hashing `hash_input`, such as the flow's addresses and ports.
+    /// Packets of the same flow always leave the same nic. Returns `None` if no linked nic's subnet contains `dst`.
+    pub fn select_egress(&self, dst: &Ipv4Address, hash_input: &[u8]) -> Option<&Nic> {
+        let routes = || {
+            self.iter()
+                .filter(|nic| nic.link_id.is_some())
+                .filter_map(|nic| Some((nic, nic.ip?)))
+                .filter(|(_, cidr)| cidr.contains_addr(dst))
+        };
+        let longest = routes().map(|(_, cidr)| cidr.prefix_len()).max()?;
+        let equal_cost: Vec<&Nic> = routes()
+            .filter(|(_, cidr)| cidr.prefix_len() == longest)
+            .map(|(nic, _)| nic)
+            .collect();
+        let pick = fnv1a(hash_input) % equal_cost.len() as u64;
+        Some(equal_cost[pick as usize])
+    }
+
+    /// A linked nic picked uniformly at random with `rng`, with the id of its partner.
+    /// Returns `None` if no nic is linked.
+    pub fn random_neighbor(&self, rng: &mut Rng) -> Option<(&Nic, NicId)> {
+        let count = self.linked_nics().count() as u64;
+        if count == 0 {
+            return None;
+        }
+        self.linked_nics().nth((rng.next_u64() % count) as usize)
+    }
+
     pub fn len(&self) -> usize {
         self.nics.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.nics.is_empty()
+    }
 }
 
 impl<'a> Index<usize> for Nics<'a> {
@@ -67,15 +343,38 @@ impl<'a> Index<usize> for Nics<'a> {
 pub struct NicsMut<'a> {
     node: usize,
     topology: &'a mut Topology,
+    /// The last neighbor mac that `link` could not find.
+    unresolved: Option<EthernetAddress>,
 }
 
 impl<'a> NicsMut<'a> {
     pub(crate) fn from_slice(node: usize, topology: &'a mut Topology) -> Self {
         // let sectioned: Vec<&mut [Nic]> = nics.chunk_by_mut(|l, r| l.group == r.group).collect();
-        Self { node, topology }
+        Self {
+            node,
+            topology,
+            unresolved: None,
+        }
+    }
+
+    /// Run a node's `try_startup`, turning a failure into a `SimErr` for the node.
+    pub(crate) fn startup(&mut self, node: &mut dyn Node) -> Result<(), SimErr> {
+        node.try_startup(self)
+            .map_err(|error| match (error, self.unresolved) {
+                (NicError::NeighborNotFound, Some(mac)) => SimErr::UnresolvedNeighbor {
+                    node: self.node,
+                    mac,
+                },
+                (error, _) => SimErr::StartupFailed {
+                    node: self.node,
+                    error,
+                },
+            })
     }
 
     /// Link with other nodes
+    ///
+    /// Fails with `NicError::SelfLink` if `next_hop` is the local nic's own MAC.
     pub fn link(&mut self, local_id: NicId, next_hop: &EthernetAddress) -> Result<(), NicError> {
         // Ensure the nic currently is not in use
 
@@ -85,12 +384,82 @@ impl<'a> NicsMut<'a> {
             .iter()
             .find(|nic| nic.mac == *next_hop)
         {
+            if neighbor.id == local_id {
+                return Err(NicError::SelfLink);
+            }
             self.topology.link_nics(local_id, neighbor.id);
             Ok(())
         } else {
+            self.unresolved = Some(*next_hop);
             Err(NicError::NeighborNotFound)
         }
     }
+
+    /// Bring the local nic up or down, keeping its link. Returns `false` if the nic does not exist.
+    pub fn set_enabled(&mut self, local_id: NicId, enabled: bool) -> bool {
+        self.topology.set_enabled(local_id, enabled)
+    }
+
+    /// Like `link`, giving the new link the figures of `profile`.
+    pub fn link_with_profile(
+        &mut self,
+        local_id: NicId,
+        next_hop: &EthernetAddress,
+        profile: LinkProfile,
+    ) -> Result<(), NicError> {
+        self.link(local_id, next_hop)?;
+        let link = self
+            .topology
+            .links
+            .last()
+            .expect("a link should have just been made")
+            .id;
+        self.topology.set_profile(link, profile);
+        Ok(())
+    }
+
+    /// Change what sending out of the local nic costs. Returns `false` if the nic does not exist.
+    pub fn set_cost(&mut self, local_id: NicId, cost: u32) -> bool {
+        self.topology.set_cost(local_id, cost)
+    }
+
+    /// Receive the messages neighbors multicast to `group` on the local nic.
+    pub fn join_group(&mut self, local_id: NicId, group: MulticastGroup) {
+        self.topology.join_group(local_id, group);
+    }
+
+    /// Link with the first nic of `target_node` that is not yet part of a link.
+    ///
+    /// Fails with `NicError::NeighborNotFound` if there is no such node,
+    /// or `NicError::NoFreeNic` if all of its nics are in use.
+    /// Fails with `NicError::SelfLink` if the free nic is the local one.
+    pub fn connect_to_node(&mut self, local_id: NicId, target_node: usize) -> Result<(), NicError> {
+        if target_node >= self.topology.node_count() {
+            return Err(NicError::NeighborNotFound);
+        }
+        let neighbor = self
+            .topology
+            .free_nic(target_node)
+            .ok_or(NicError::NoFreeNic)?;
+        if neighbor == local_id {
+            return Err(NicError::SelfLink);
+        }
+        self.topology.link_nics(local_id, neighbor);
+        Ok(())
+    }
+
+    /// Tear down the link the local nic is part of.
+    pub fn unlink(&mut self, local_id: NicId) -> Result<(), NicError> {
+        let link = self
+            .topology
+            .links
+            .iter()
+            .find(|link| link.partner(local_id).is_some())
+            .ok_or(NicError::NotLinked)?
+            .id;
+        self.topology.unlink(link);
+        Ok(())
+    }
 }
 
 impl<'a> Index<usize> for NicsMut<'a> {
@@ -113,6 +482,11 @@ pub struct NicAllocator {
     /// nic-group; Binding nics to respective nodes.
     ngroup: u64,
     nics: Vec<Nic>,
+    /// Where the nics of the node currently being allocated for begin in `nics`.
+    node_start: usize,
+    /// Every mac in the simulation, so generated ones are unique.
+    macs: HashSet<EthernetAddress>,
+    rng: Rng,
 }
 
 impl NicAllocator {
@@ -121,35 +495,175 @@ impl NicAllocator {
     /// # Panics!
     /// If the total number of nics generated in the simulation exceeds the capacity of a `u64`.
     pub fn nic(&mut self, mac: EthernetAddress, latency: Option<u64>) {
+        self.nic_with_mtu(mac, latency, DEFAULT_MTU);
+    }
+
+    /// Add a nic to the node that sends messages of at most `mtu` bytes.
+    ///
+    /// # Panics!
+    /// If the total number of nics generated in the simulation exceeds the capacity of a `u64`.
+    pub fn nic_with_mtu(&mut self, mac: EthernetAddress, latency: Option<u64>, mtu: usize) {
         let next_id = self.nid;
         self.nid = self
             .nid
             .checked_add(1)
             .expect("The number of nics should be less than or equal to `u64::MAX`");
+        self.macs.insert(mac);
         self.nics.push(Nic {
             id: next_id,
             group: self.ngroup,
             mac,
             latency,
+            mtu,
+            enabled: true,
+            label: None,
+            cost: default_cost(),
+            ip: None,
             link_id: None,
+            partner: None,
+            partner_mac: None,
+            partner_node: None,
+            receive_only: false,
+            metadata: Metadata::default(),
         });
     }
 
+    /// Add a nic to the node that can be found again with `Nics::find_label`, returning its id.
+    ///
+    /// # Panics!
+    /// If the node already has a nic labelled `label`,
+    /// or the total number of nics generated in the simulation exceeds the capacity of a `u64`.
+    pub fn nic_named(&mut self, label: &str, mac: EthernetAddress, latency: Option<u64>) -> NicId {
+        assert!(
+            self.nics[self.node_start..]
+                .iter()
+                .all(|nic| nic.label.as_deref() != Some(label)),
+            "nic labels should be unique within a node"
+        );
+        let id = self.nid;
+        self.nic(mac, latency);
+        self.nics
+            .last_mut()
+            .expect("a nic should have just been added")
+            .label = Some(label.to_string());
+        id
+    }
+
+    /// Add a nic to the node with the IPv4 address and subnet `ip`, returning its id.
+    ///
+    /// # Panics!
+    /// If the total number of nics generated in the simulation exceeds the capacity of a `u64`.
+    pub fn nic_with_ip(
+        &mut self,
+        mac: EthernetAddress,
+        latency: Option<u64>,
+        ip: Ipv4Cidr,
+    ) -> NicId {
+        let id = self.nid;
+        self.nic(mac, latency);
+        self.nics
+            .last_mut()
+            .expect("a nic should have just been added")
+            .ip = Some(ip);
+        id
+    }
+
+    /// Add a nic to the node that costs `cost` to send out of when routing by cost, returning its id.
+    ///
+    /// # Panics!
+    /// If the total number of nics generated in the simulation exceeds the capacity of a `u64`.
+    pub fn nic_with_cost(
+        &mut self,
+        mac: EthernetAddress,
+        latency: Option<u64>,
+        cost: u32,
+    ) -> NicId {
+        let id = self.nid;
+        self.nic(mac, latency);
+        self.nics
+            .last_mut()
+            .expect("a nic should have just been added")
+            .cost = cost;
+        id
+    }
+
+    /// Add a nic to the node carrying `metadata`, to be read back with `Nic::metadata`, returning its id.
+    ///
+    /// # Panics!
+    /// If the total number of nics generated in the simulation exceeds the capacity of a `u64`.
+    pub fn nic_with_metadata(
+        &mut self,
+        mac: EthernetAddress,
+        latency: Option<u64>,
+        metadata: impl Any + Send + Sync,
+    ) -> NicId {
+        let id = self.nid;
+        self.nic(mac, latency);
+        self.nics
+            .last_mut()
+            .expect("a nic should have just been added")
+            .metadata = Metadata(Some(Arc::new(metadata)));
+        id
+    }
+
+    /// Add a nic with a generated mac to the node, returning its id.
+    ///
+    /// The mac is a locally administered unicast address, unique within the simulation.
+    ///
+    /// # Panics!
+    /// If the total number of nics generated in the simulation exceeds the capacity of a `u64`.
+    pub fn nic_auto(&mut self, latency: Option<u64>) -> NicId {
+        let mac = loop {
+            let bytes = self.rng.next_u64().to_be_bytes();
+            let mut mac = [0; 6];
+            mac.copy_from_slice(&bytes[..6]);
+            // Set the locally administered bit and clear the multicast bit.
+            mac[0] = (mac[0] | 0x02) & !0x01;
+            let mac = EthernetAddress(mac);
+            if !self.macs.contains(&mac) {
+                break mac;
+            }
+        };
+        let id = self.nid;
+        self.nic(mac, latency);
+        id
+    }
+
     pub(crate) fn with_capacity(capacity: usize) -> Self {
         Self {
             nid: 0,
             ngroup: 0,
             nics: Vec::with_capacity(capacity),
+            node_start: 0,
+            macs: HashSet::new(),
+            rng: Rng::new(0),
+        }
+    }
+
+    /// Continue allocating after the hardware of an existing simulation.
+    pub(crate) fn starting_from(ngroup: NicGroup, existing: &[Nic]) -> Self {
+        let nid = existing.len() as NicId;
+        Self {
+            nid,
+            ngroup,
+            nics: Vec::new(),
+            node_start: 0,
+            macs: existing.iter().map(|nic| nic.mac).collect(),
+            rng: Rng::new(nid),
         }
     }
 
-    /// If a node has not initialized at least one NIC.
-    pub(crate) fn next_node(&mut self) -> Result<(), SimErr> {
-        // Assert that at least one NIC has been initialized by the user
-        // assert_eq!(self.ngroup, self.nics[self.nics.len() - 1].group);
-        if self.nics.len() <= 0 || self.ngroup != self.nics[self.nics.len() - 1].group {
-            return Result::Err(SimErr::NodeNoHardware);
+    /// Finish allocating for the current node, called `name`.
+    ///
+    /// Fails with `SimErr::NodeNoHardware` if the node did not add at least one nic.
+    pub(crate) fn next_node(&mut self, name: &str) -> Result<(), SimErr> {
+        if self.nics.len() == self.node_start {
+            return Err(SimErr::NodeNoHardware {
+                node: self.ngroup as usize,
+                name: name.to_string(),
+            });
         }
+        self.node_start = self.nics.len();
         self.ngroup = self
             .ngroup
             .checked_add(1)
@@ -157,7 +671,7 @@ impl NicAllocator {
         Ok(())
     }
 
-    pub(crate) fn to_vec(self) -> Vec<Nic> {
+    pub(crate) fn into_vec(self) -> Vec<Nic> {
         self.nics
     }
 
@@ -165,3 +679,62 @@ impl NicAllocator {
         self.nics.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nic_auto_macs_are_unique_and_local() {
+        let mut nics = NicAllocator::with_capacity(100);
+        for id in 0..100 {
+            assert_eq!(nics.nic_auto(None), id);
+        }
+        let macs: HashSet<_> = nics.into_vec().iter().map(|nic| nic.mac).collect();
+        assert_eq!(macs.len(), 100);
+        for mac in macs {
+            assert!(mac.is_unicast());
+            assert!(mac.is_local());
+        }
+    }
+
+    #[test]
+    fn nic_named_lookup() {
+        let mut allocator = NicAllocator::with_capacity(2);
+        let wan = allocator.nic_named("wan", EthernetAddress([2, 0, 0, 0, 0, 1]), None);
+        let lan = allocator.nic_named("lan", EthernetAddress([2, 0, 0, 0, 0, 2]), Some(5));
+        let hardware = allocator.into_vec();
+        let nics = Nics::from_slice(&hardware);
+
+        assert_eq!(nics.find_label("wan").map(|nic| nic.id), Some(wan));
+        assert_eq!(nics.find_label("lan").map(|nic| nic.id), Some(lan));
+        assert!(nics.find_label("dmz").is_none());
+    }
+
+    #[test]
+    fn macs_of_every_nic() {
+        let mut allocator = NicAllocator::with_capacity(2);
+        allocator.nic(EthernetAddress([2, 0, 0, 0, 0, 1]), None);
+        allocator.nic(EthernetAddress([2, 0, 0, 0, 0, 2]), None);
+        let hardware = allocator.into_vec();
+        let macs: Vec<_> = Nics::from_slice(&hardware).macs().collect();
+        assert_eq!(
+            macs,
+            [
+                EthernetAddress([2, 0, 0, 0, 0, 1]),
+                EthernetAddress([2, 0, 0, 0, 0, 2])
+            ]
+        );
+    }
+
+    #[test]
+    fn one_nic_is_not_empty() {
+        let mut allocator = NicAllocator::with_capacity(1);
+        allocator.nic(EthernetAddress([2, 0, 0, 0, 0, 1]), None);
+        let hardware = allocator.into_vec();
+        let nics = Nics::from_slice(&hardware);
+        assert_eq!(nics.len(), 1);
+        assert!(!nics.is_empty());
+        assert!(Nics::from_slice(&[]).is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use smoltcp::phy::{self, Device, DeviceCapabilities, Medium};
+use smoltcp::time::Instant;
+
+use crate::message::Mailbox;
+use crate::nics::Nic;
+
+/// Exposes a nic to smoltcp, so a node can run a `smoltcp::iface::Interface` over the simulation.
+///
+/// Frames transmitted by smoltcp are sent out of the nic, and frames received are those
+/// queued in the mailbox for the nic. Create one while processing, and poll the interface
+/// with it whenever the node is woken.
+pub struct NicDevice<'a> {
+    mail: &'a mut Mailbox,
+    nic: &'a Nic,
+}
+
+impl<'a> NicDevice<'a> {
+    pub fn new(mail: &'a mut Mailbox, nic: &'a Nic) -> Self {
+        Self { mail, nic }
+    }
+}
+
+impl Device for NicDevice<'_> {
+    type RxToken<'b>
+        = RxToken
+    where
+        Self: 'b;
+    type TxToken<'b>
+        = TxToken<'b>
+    where
+        Self: 'b;
+
+    fn receive(&mut self, _: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let msg = self.mail.try_recv_on(self.nic.id)?;
+        Some((
+            RxToken {
+                frame: msg.into_data(),
+            },
+            TxToken {
+                mail: self.mail,
+                nic: self.nic,
+            },
+        ))
+    }
+
+    fn transmit(&mut self, _: Instant) -> Option<Self::TxToken<'_>> {
+        Some(TxToken {
+            mail: self.mail,
+            nic: self.nic,
+        })
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut capabilities = DeviceCapabilities::default();
+        capabilities.medium = Medium::Ethernet;
+        capabilities.max_transmission_unit = self.nic.mtu;
+        capabilities
+    }
+}
+
+pub struct RxToken {
+    frame: Vec<u8>,
+}
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        f(&self.frame)
+    }
+}
+
+pub struct TxToken<'a> {
+    mail: &'a mut Mailbox,
+    nic: &'a Nic,
+}
+
+impl phy::TxToken for TxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut frame = vec![0; len];
+        let result = f(&mut frame);
+        // Like a real interface, frames sent while the link is down are lost.
+        let _ = self.mail.send(self.nic, frame);
+        result
+    }
+}
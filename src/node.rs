@@ -1,8 +1,20 @@
+use std::future::Future;
 use std::pin::Pin;
 
 use async_trait::async_trait;
 
-use crate::{message::Mailbox, nics::*};
+use crate::{
+    message::{IncomingMsg, Mailbox},
+    nics::*,
+};
+
+pub mod arp;
+#[cfg(feature = "host-io")]
+pub mod external;
+pub mod ip;
+pub mod probe;
+pub mod router;
+pub mod switch;
 
 #[macro_export]
 macro_rules! nodes {
@@ -11,7 +23,38 @@ macro_rules! nodes {
     }
 }
 
-pub enum NodeError {}
+/// Box each node into a `Vec<Box<dyn Node>>`, for `run_sim_boxed`.
+#[macro_export]
+macro_rules! nodes_vec {
+    ( $( $x:expr ),* $(,)? ) => {
+        vec![$( Box::new($x) as Box<dyn $crate::node::Node>, )*]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NodeError {
+    /// The nic is not linked to a neighbor.
+    LinkDown,
+    /// The message is larger than the nic's mtu.
+    FrameTooLarge,
+    /// None of the node's linked nics has a partner with the destination MAC.
+    Unreachable,
+    /// Talking to the host failed, such as by an `ExternalNode`'s socket.
+    #[cfg(feature = "host-io")]
+    HostIo(std::io::ErrorKind),
+}
+
+/// Whether to let a message, arriving on the given nics, into a node's mailbox. See `Node::filter`.
+pub type MessageFilter = Box<dyn Fn(&IncomingMsg, &Nics<'_>) -> bool>;
+
+/// Whether a node wants to keep being activated after `process` returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Continue {
+    /// Call `process` again when new messages arrive.
+    KeepRunning,
+    /// The node is finished, and is never polled again. Messages sent to it are left unread.
+    Done,
+}
 
 #[async_trait]
 pub trait Node {
@@ -28,6 +71,64 @@ pub trait Node {
     /// Connect to other devices.
     fn startup(&mut self, nics: &mut NicsMut<'_>);
 
-    /// Called when the node's `Mailbox` has incoming messages.
-    async fn process(&mut self, mail: &mut Mailbox, nics: &Nics<'_>) -> Result<(), NodeError>;
+    /// Connect to other devices, returning link failures instead of panicking on them.
+    /// This is what the simulator calls; by default it runs `startup`.
+    fn try_startup(&mut self, nics: &mut NicsMut<'_>) -> Result<(), NicError> {
+        self.startup(nics);
+        Ok(())
+    }
+
+    /// Forget what the last trial left behind, when `Sim::reset` is about to run `startup` again.
+    /// Does nothing by default.
+    fn reset(&mut self) {}
+
+    /// Whether to let `msg` into the node's mailbox. Rejected messages are dropped as `DropReason::Filtered`.
+    ///
+    /// Consulted at delivery while the node is between calls to `process`, which suits nodes that drain
+    /// their mailbox and return, and only when `filter` gives none. Lets every message in by default.
+    fn accept(&self, _msg: &IncomingMsg, _nics: &Nics<'_>) -> bool {
+        true
+    }
+
+    /// A filter deciding which messages to let into the node's mailbox, in place of `accept`. Read when the
+    /// node joins the simulation and after each `reset`, and consulted at delivery whatever the node is
+    /// doing, so nodes that wait on their mailbox inside `process` can filter too. None by default.
+    fn filter(&self) -> Option<MessageFilter> {
+        None
+    }
+
+    /// Nanoseconds the node takes to handle a message, holding back whatever it sends in response
+    /// to one that arrived at the same instant. Read once, when the node joins the simulation. 0 by default.
+    fn processing_delay(&self) -> u64 {
+        0
+    }
+
+    /// Called when a link of the node goes down or comes back up while the simulation runs, such as by
+    /// `Sim::set_enabled` or `Sim::unlink`, with the local nic on it and the nics as they are now.
+    ///
+    /// Runs between calls to `process`, so a node busy in `process`, such as waiting in `Mailbox::recv`,
+    /// hears of the change only once it returns. Until then its `Nics` are as they were when `process`
+    /// was called, though `Mailbox::send` fails on a link that has since gone down. Links made by a node
+    /// joining through `Sim::add_node` are not reported. Does nothing by default. Implement it as an
+    /// `async fn` like `process`.
+    // Written out as `async_trait` would expand it, since a default `async fn` would need `Self: Send`.
+    fn on_link_change<'life0, 'life1, 'life2, 'async_trait>(
+        &'life0 mut self,
+        _nic: NicId,
+        _up: bool,
+        _nics: &'life1 Nics<'life2>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        'life2: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async {})
+    }
+
+    /// Called once when the simulation starts, then whenever the node's `Mailbox` has incoming messages,
+    /// until it returns `Continue::Done`.
+    async fn process(&mut self, mail: &mut Mailbox, nics: &Nics<'_>)
+        -> Result<Continue, NodeError>;
 }
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, VecDeque};
+
+use async_trait::async_trait;
+use smoltcp::wire::{
+    ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetProtocol, Ipv4Address,
+};
+
+use crate::message::frame::Frame;
+use crate::message::{IncomingMsg, Mailbox};
+use crate::nics::{NicAllocator, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// Resolves IPv4 addresses to MACs with ARP, and answers requests for its own address.
+///
+/// Keep one per node across calls to `process`, and use its `recv` in place of `Mailbox::recv`
+/// so requests are answered whenever the node is waiting for mail.
+pub struct ArpTable {
+    ip: Ipv4Address,
+    table: HashMap<Ipv4Address, EthernetAddress>,
+    /// Messages other than ARP received while resolving, kept for `recv`.
+    received: VecDeque<IncomingMsg>,
+}
+
+impl ArpTable {
+    /// A table for a node that owns `ip`.
+    pub fn new(ip: Ipv4Address) -> Self {
+        Self {
+            ip,
+            table: HashMap::new(),
+            received: VecDeque::new(),
+        }
+    }
+
+    /// The MAC `ip` has been resolved to, if any.
+    pub fn lookup(&self, ip: &Ipv4Address) -> Option<EthernetAddress> {
+        self.table.get(ip).copied()
+    }
+
+    /// The MAC that owns `ip`, broadcasting a request out of every linked nic unless it is already known,
+    /// then waiting for the reply. Other messages that arrive in the meantime are kept for `recv`.
+    pub async fn resolve(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+        ip: Ipv4Address,
+    ) -> Result<EthernetAddress, NodeError> {
+        if let Some(mac) = self.lookup(&ip) {
+            return Ok(mac);
+        }
+        for nic in nics.iter().filter(|nic| nic.link_id.is_some()) {
+            let request = ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                source_hardware_addr: nic.mac,
+                source_protocol_addr: self.ip,
+                target_hardware_addr: EthernetAddress([0; 6]),
+                target_protocol_addr: ip,
+            };
+            mail.send_frame(nic, &frame(request, nic.mac, EthernetAddress::BROADCAST))?;
+        }
+        loop {
+            let msg = mail.recv().await;
+            if self.accept(mail, nics, msg)? {
+                if let Some(mac) = self.lookup(&ip) {
+                    return Ok(mac);
+                }
+            }
+        }
+    }
+
+    /// Wait for the next message that isn't ARP, answering and learning from ARP frames meanwhile.
+    pub async fn recv(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<IncomingMsg, NodeError> {
+        loop {
+            if let Some(msg) = self.received.pop_front() {
+                return Ok(msg);
+            }
+            let msg = mail.recv().await;
+            self.accept(mail, nics, msg)?;
+        }
+    }
+
+    /// Learn the sender of an ARP frame, replying if it asks for our address, or keep any other message.
+    /// Returns whether the message was ARP.
+    fn accept(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+        msg: IncomingMsg,
+    ) -> Result<bool, NodeError> {
+        let Some(repr) = parse(&msg) else {
+            self.received.push_back(msg);
+            return Ok(false);
+        };
+        let ArpRepr::EthernetIpv4 {
+            operation,
+            source_hardware_addr,
+            source_protocol_addr,
+            target_protocol_addr,
+            ..
+        } = repr
+        else {
+            return Ok(true);
+        };
+        self.table
+            .insert(source_protocol_addr, source_hardware_addr);
+        if operation == ArpOperation::Request && target_protocol_addr == self.ip {
+            if let Some(nic) = nics.find_id(msg.to()) {
+                let reply = ArpRepr::EthernetIpv4 {
+                    operation: ArpOperation::Reply,
+                    source_hardware_addr: nic.mac,
+                    source_protocol_addr: self.ip,
+                    target_hardware_addr: source_hardware_addr,
+                    target_protocol_addr: source_protocol_addr,
+                };
+                mail.send_frame(nic, &frame(reply, nic.mac, source_hardware_addr))?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A host with one nic that owns an IPv4 address, and does nothing but answer ARP requests for it.
+pub struct ArpNode {
+    mac: EthernetAddress,
+    table: ArpTable,
+}
+
+impl ArpNode {
+    /// A host at `mac`. Neighbors link to it in their own `startup`.
+    pub fn new(mac: EthernetAddress, ip: Ipv4Address) -> Self {
+        Self {
+            mac,
+            table: ArpTable::new(ip),
+        }
+    }
+
+    /// The addresses learned from requests the node has seen.
+    pub fn table(&self) -> &ArpTable {
+        &self.table
+    }
+}
+
+#[async_trait]
+impl Node for ArpNode {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.mac, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        // Anything that isn't ARP is dropped.
+        self.table.recv(mail, nics).await?;
+        Ok(Continue::KeepRunning)
+    }
+}
+
+fn frame(repr: ArpRepr, src_mac: EthernetAddress, dst_mac: EthernetAddress) -> Frame {
+    let mut payload = vec![0; repr.buffer_len()];
+    repr.emit(&mut ArpPacket::new_unchecked(&mut payload));
+    Frame::new(src_mac, dst_mac, EthernetProtocol::Arp, payload)
+}
+
+/// The ARP packet carried by `msg`, if it is an ARP frame.
+fn parse(msg: &IncomingMsg) -> Option<ArpRepr> {
+    let frame = Frame::parse(msg.data())?;
+    if frame.ethertype != EthernetProtocol::Arp {
+        return None;
+    }
+    ArpRepr::parse(&ArpPacket::new_checked(&frame.payload).ok()?).ok()
+}
@@ -0,0 +1,115 @@
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use smoltcp::wire::EthernetAddress;
+
+use crate::message::Mailbox;
+use crate::nics::{NicAllocator, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// How often, in virtual time, `ExternalNode` checks the socket unless told otherwise: one millisecond.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How many checks in a row may come up empty before `ExternalNode` waits for mail instead.
+const DEFAULT_IDLE_POLLS: usize = 1000;
+
+/// Bridges one nic to a UDP socket on the host, so the simulation can talk to real software.
+///
+/// Each datagram from the host is sent into the simulation as one frame, and each frame the node
+/// receives is written out to `peer` as one datagram. The socket is checked every `poll_interval`
+/// of virtual time, until `idle_polls` checks in a row find nothing; mail from the simulation starts
+/// the checks again. Run the simulation with `run_sim_realtime` so virtual time keeps pace with the host.
+pub struct ExternalNode {
+    mac: EthernetAddress,
+    socket: UdpSocket,
+    peer: SocketAddr,
+    poll_interval: Duration,
+    idle_polls: usize,
+}
+
+impl ExternalNode {
+    /// A node at `mac` that trades frames with `peer` over `socket`. Neighbors link to it in their own `startup`.
+    ///
+    /// Fails if the socket cannot be made non-blocking.
+    pub fn new(mac: EthernetAddress, socket: UdpSocket, peer: SocketAddr) -> std::io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            mac,
+            socket,
+            peer,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            idle_polls: DEFAULT_IDLE_POLLS,
+        })
+    }
+
+    /// Check the socket every `interval` of virtual time instead of every millisecond.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Stop checking the socket after `polls` empty checks in a row instead of 1000.
+    pub fn idle_polls(mut self, polls: usize) -> Self {
+        self.idle_polls = polls;
+        self
+    }
+
+    /// Send every datagram waiting on the socket into the simulation, returning how many there were.
+    fn take_from_host(&self, mail: &mut Mailbox, nics: &Nics<'_>) -> Result<usize, NodeError> {
+        let mut buf = vec![0; nics[0].mtu];
+        let mut count = 0;
+        loop {
+            match self.socket.recv(&mut buf) {
+                Ok(len) => {
+                    // A datagram the nic can't carry, such as while it is receive only, is dropped.
+                    mail.send_or_drop(&nics[0], buf[..len].to_vec())?;
+                    count += 1;
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(count),
+                Err(error) => return Err(NodeError::HostIo(error.kind())),
+            }
+        }
+    }
+
+    fn send_to_host(&self, data: &[u8]) -> Result<(), NodeError> {
+        self.socket
+            .send_to(data, self.peer)
+            .map(|_| ())
+            .map_err(|error| NodeError::HostIo(error.kind()))
+    }
+}
+
+#[async_trait]
+impl Node for ExternalNode {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.mac, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        let mut idle = 0;
+        while idle < self.idle_polls {
+            while let Some(msg) = mail.try_recv() {
+                self.send_to_host(msg.data())?;
+            }
+            // Frames can only go in once the nic is linked.
+            let received = match nics[0].link_id {
+                Some(_) => self.take_from_host(mail, nics)?,
+                None => 0,
+            };
+            idle = if received > 0 { 0 } else { idle + 1 };
+            if let Some(msg) = mail.recv_timeout(self.poll_interval).await {
+                self.send_to_host(msg.data())?;
+                idle = 0;
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
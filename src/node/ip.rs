@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::wire::{EthernetAddress, IpProtocol, Ipv4Address, Ipv4Cidr, Ipv4Packet, Ipv4Repr};
+
+use crate::message::Mailbox;
+use crate::nics::{NicAllocator, NicId, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// The protocol number `packet` marks its payload with, reserved for experiments.
+const EXPERIMENTAL: IpProtocol = IpProtocol::Unknown(253);
+
+/// An IPv4 packet from `src` to `dst` carrying `payload`, that may pass through `ttl` routers.
+pub fn packet(src: Ipv4Address, dst: Ipv4Address, ttl: u8, payload: &[u8]) -> Vec<u8> {
+    let repr = Ipv4Repr {
+        src_addr: src,
+        dst_addr: dst,
+        next_header: EXPERIMENTAL,
+        payload_len: payload.len(),
+        hop_limit: ttl,
+    };
+    let mut bytes = vec![0; repr.buffer_len() + payload.len()];
+    let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+    repr.emit(&mut packet, &ChecksumCapabilities::default());
+    packet.payload_mut().copy_from_slice(payload);
+    bytes
+}
+
+/// The nic whose subnet most specifically contains `dst`, if any.
+pub fn longest_prefix_match(nics: &Nics<'_>, dst: &Ipv4Address) -> Option<NicId> {
+    nics.iter()
+        .filter_map(|nic| Some((nic.id, nic.ip?)))
+        .filter(|(_, cidr)| cidr.contains_addr(dst))
+        .max_by_key(|(_, cidr)| cidr.prefix_len())
+        .map(|(id, _)| id)
+}
+
+/// Forwards IPv4 packets between the subnets of its nics.
+///
+/// Each packet goes out of the nic whose subnet is the longest prefix match for its destination,
+/// with its TTL decremented. Packets for the node's own addresses are kept, and malformed packets,
+/// expired ones and those no subnet matches are dropped.
+pub struct IpNode {
+    ports: Vec<(EthernetAddress, Ipv4Cidr)>,
+    received: Vec<Vec<u8>>,
+}
+
+impl IpNode {
+    /// A node with a port for each MAC, on each subnet. Neighbors link to the ports in their own `startup`.
+    pub fn new(ports: Vec<(EthernetAddress, Ipv4Cidr)>) -> Self {
+        Self {
+            ports,
+            received: Vec::new(),
+        }
+    }
+
+    /// Packets addressed to one of the node's own addresses.
+    pub fn received(&self) -> &[Vec<u8>] {
+        &self.received
+    }
+}
+
+#[async_trait]
+impl Node for IpNode {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for (mac, ip) in &self.ports {
+            nics.nic_with_ip(*mac, None, *ip);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            let mut data = msg.into_data();
+            let Ok(mut packet) = Ipv4Packet::new_checked(&mut data[..]) else {
+                continue;
+            };
+            let dst = packet.dst_addr();
+            if nics
+                .iter()
+                .any(|nic| nic.ip.is_some_and(|ip| ip.address() == dst))
+            {
+                self.received.push(data);
+                continue;
+            }
+            if packet.hop_limit() <= 1 {
+                continue;
+            }
+            let Some(port) = longest_prefix_match(nics, &dst).and_then(|id| nics.find_id(id))
+            else {
+                continue;
+            };
+            packet.set_hop_limit(packet.hop_limit() - 1);
+            packet.fill_checksum();
+            mail.send_or_drop(port, data)?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
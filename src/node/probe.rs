@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use smoltcp::wire::EthernetAddress;
+
+use crate::message::Mailbox;
+use crate::nics::{NicAllocator, NicId, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// A frame a `ProbeNode` received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedFrame {
+    /// When the frame arrived, in virtual time.
+    pub at: u64,
+    /// The nic that sent it.
+    pub from: NicId,
+    /// The probe's nic it arrived on.
+    pub to: NicId,
+    pub data: Vec<u8>,
+}
+
+/// Records every frame it receives, like tcpdump.
+///
+/// With one nic the probe is an endpoint that swallows what it captures. With two it sits
+/// inline, passing each frame on out of the other nic, so it can be wired between two nodes.
+pub struct ProbeNode {
+    macs: Vec<EthernetAddress>,
+    captured: Vec<CapturedFrame>,
+    dropped: usize,
+}
+
+impl ProbeNode {
+    /// A probe at `mac`. Neighbors link to it in their own `startup`.
+    pub fn new(mac: EthernetAddress) -> Self {
+        Self {
+            macs: vec![mac],
+            captured: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// A probe between the nodes linking to `a` and `b`, forwarding between them.
+    pub fn inline(a: EthernetAddress, b: EthernetAddress) -> Self {
+        Self {
+            macs: vec![a, b],
+            captured: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Every frame received so far, in order of arrival.
+    pub fn captured(&self) -> &[CapturedFrame] {
+        &self.captured
+    }
+
+    /// How many captured frames an inline probe could not pass on, as the other nic can't carry them.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[async_trait]
+impl Node for ProbeNode {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for mac in &self.macs {
+            nics.nic(*mac, None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            let frame = CapturedFrame {
+                at: mail.now(),
+                from: msg.from(),
+                to: msg.to(),
+                data: msg.into_data(),
+            };
+            if nics.len() == 2 {
+                let out = if frame.to == nics[0].id {
+                    &nics[1]
+                } else {
+                    &nics[0]
+                };
+                if out.link_id.is_some() && !mail.send_or_drop(out, frame.data.clone())? {
+                    self.dropped += 1;
+                }
+            }
+            self.captured.push(frame);
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
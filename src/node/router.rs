@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use smoltcp::wire::EthernetAddress;
+
+use crate::message::Mailbox;
+use crate::nics::{NicAllocator, NicGroup, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// A payload addressed to a node, as forwarded by `RouterNode`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RoutedPacket {
+    /// The destination node.
+    pub dst: NicGroup,
+    /// The number of routers the packet has passed through.
+    pub hops: u8,
+    pub payload: Vec<u8>,
+}
+
+impl RoutedPacket {
+    /// Destination then hop count.
+    const HEADER_LEN: usize = 9;
+
+    pub fn new(dst: NicGroup, payload: Vec<u8>) -> Self {
+        Self {
+            dst,
+            hops: 0,
+            payload,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::HEADER_LEN + self.payload.len());
+        bytes.extend_from_slice(&self.dst.to_be_bytes());
+        bytes.push(self.hops);
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// Returns `None` if `data` is too short to hold the header.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::HEADER_LEN {
+            return None;
+        }
+        let (dst, rest) = data.split_at(8);
+        Some(Self {
+            dst: NicGroup::from_be_bytes(dst.try_into().ok()?),
+            hops: rest[0],
+            payload: rest[1..].to_vec(),
+        })
+    }
+}
+
+/// Forwards `RoutedPacket`s by a static table of destination node to port.
+pub struct RouterNode {
+    ports: Vec<EthernetAddress>,
+    /// Destination node to the index of the port that reaches it.
+    routes: HashMap<NicGroup, usize>,
+}
+
+impl RouterNode {
+    /// A router with a port for each MAC. Neighbors link to the ports in their own `startup`.
+    ///
+    /// # Panics!
+    /// When forwarding along a route to a port index the router doesn't have.
+    pub fn new(ports: Vec<EthernetAddress>, routes: HashMap<NicGroup, usize>) -> Self {
+        Self { ports, routes }
+    }
+}
+
+#[async_trait]
+impl Node for RouterNode {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for port in &self.ports {
+            nics.nic(*port, None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            // Malformed packets and unknown destinations are dropped.
+            let Some(mut packet) = RoutedPacket::parse(msg.data()) else {
+                continue;
+            };
+            let Some(&port) = self.routes.get(&packet.dst) else {
+                continue;
+            };
+            packet.hops = packet.hops.saturating_add(1);
+            mail.send_or_drop(&nics[port], packet.to_bytes())?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use smoltcp::wire::{EthernetAddress, EthernetFrame};
+
+use crate::message::Mailbox;
+use crate::nics::{NicAllocator, NicId, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// The ethertype of an 802.1Q tag, which sits between the MACs and the frame's own ethertype.
+const VLAN_TPID: [u8; 2] = [0x81, 0x00];
+/// Where the tag goes: after the destination and source MACs.
+const TAG_OFFSET: usize = 12;
+const TAG_LEN: usize = 4;
+
+/// The VLANs a switch port carries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VlanPort {
+    /// One VLAN, untagged. Tagged frames arriving on the port are dropped.
+    Access(u16),
+    /// Frames tagged with any of `vlans`, and untagged frames on the `native` VLAN.
+    /// Untagged frames are dropped if there is no native VLAN.
+    Trunk {
+        vlans: Vec<u16>,
+        native: Option<u16>,
+    },
+}
+
+impl VlanPort {
+    fn carries(&self, vlan: u16) -> bool {
+        match self {
+            VlanPort::Access(access) => *access == vlan,
+            VlanPort::Trunk { vlans, native } => vlans.contains(&vlan) || *native == Some(vlan),
+        }
+    }
+
+    /// The VLAN a frame arriving with `tag` belongs to, if the port accepts it.
+    fn classify(&self, tag: Option<u16>) -> Option<u16> {
+        match (self, tag) {
+            (VlanPort::Access(vlan), None) => Some(*vlan),
+            (VlanPort::Trunk { vlans, .. }, Some(tag)) if vlans.contains(&tag) => Some(tag),
+            (VlanPort::Trunk { native, .. }, None) => *native,
+            _ => None,
+        }
+    }
+
+    /// `frame`, untagged, as it leaves the port on `vlan`.
+    fn egress(&self, frame: &[u8], vlan: u16) -> Vec<u8> {
+        match self {
+            VlanPort::Trunk { native, .. } if *native != Some(vlan) => tag_vlan(frame, vlan),
+            _ => frame.to_vec(),
+        }
+    }
+}
+
+/// `frame` with an 802.1Q tag for `vlan` inserted after its MACs.
+///
+/// # Panics!
+/// If `frame` is too short to hold both MACs.
+pub fn tag_vlan(frame: &[u8], vlan: u16) -> Vec<u8> {
+    let mut tagged = Vec::with_capacity(frame.len() + TAG_LEN);
+    tagged.extend_from_slice(&frame[..TAG_OFFSET]);
+    tagged.extend_from_slice(&VLAN_TPID);
+    tagged.extend_from_slice(&(vlan & 0x0fff).to_be_bytes());
+    tagged.extend_from_slice(&frame[TAG_OFFSET..]);
+    tagged
+}
+
+/// The VLAN `frame` is tagged with, and the frame without its tag.
+pub fn untag_vlan(frame: &[u8]) -> (Option<u16>, Vec<u8>) {
+    match frame.get(TAG_OFFSET..TAG_OFFSET + TAG_LEN) {
+        Some([tpid @ .., hi, lo]) if *tpid == VLAN_TPID => {
+            let mut untagged = frame[..TAG_OFFSET].to_vec();
+            untagged.extend_from_slice(&frame[TAG_OFFSET + TAG_LEN..]);
+            (Some(u16::from_be_bytes([*hi, *lo]) & 0x0fff), untagged)
+        }
+        _ => (None, frame.to_vec()),
+    }
+}
+
+/// A learning bridge.
+///
+/// Each frame's source MAC is remembered against the port it arrived on. Frames are
+/// forwarded out of the port their destination was learned on, or flooded out of every
+/// other linked port if the destination is unknown or broadcast.
+///
+/// A switch made with `with_vlans` keeps each VLAN apart, learning MACs per VLAN and only
+/// forwarding frames between ports that carry the frame's VLAN.
+pub struct SwitchNode {
+    ports: Vec<EthernetAddress>,
+    /// The VLANs of each port, in the order of `ports`, if the switch has any.
+    vlans: Option<Vec<VlanPort>>,
+    table: HashMap<(Option<u16>, EthernetAddress), NicId>,
+}
+
+impl SwitchNode {
+    /// A switch with a port for each MAC. Neighbors link to the ports in their own `startup`.
+    pub fn new(ports: Vec<EthernetAddress>) -> Self {
+        Self {
+            ports,
+            vlans: None,
+            table: HashMap::new(),
+        }
+    }
+
+    /// A switch with a port for each MAC, carrying the given VLANs.
+    pub fn with_vlans(ports: Vec<(EthernetAddress, VlanPort)>) -> Self {
+        let (ports, vlans) = ports.into_iter().unzip();
+        Self {
+            ports,
+            vlans: Some(vlans),
+            table: HashMap::new(),
+        }
+    }
+
+    /// The port a MAC has been learned on, on a switch without VLANs.
+    pub fn port_of(&self, mac: &EthernetAddress) -> Option<NicId> {
+        self.table.get(&(None, *mac)).copied()
+    }
+
+    /// The port a MAC has been learned on within `vlan`.
+    pub fn vlan_port_of(&self, vlan: u16, mac: &EthernetAddress) -> Option<NicId> {
+        self.table.get(&(Some(vlan), *mac)).copied()
+    }
+}
+
+/// Forward a frame that arrived on port `incoming` within `vlan`, untagged, learning its source in `table`.
+fn forward_vlan(
+    table: &mut HashMap<(Option<u16>, EthernetAddress), NicId>,
+    mail: &mut Mailbox,
+    nics: &Nics<'_>,
+    vlans: &[VlanPort],
+    incoming: NicId,
+    vlan: u16,
+    frame: Vec<u8>,
+) -> Result<(), NodeError> {
+    let Ok(parsed) = EthernetFrame::new_checked(&frame[..]) else {
+        return Ok(());
+    };
+    let (src, dst) = (parsed.src_addr(), parsed.dst_addr());
+    if src.is_unicast() {
+        table.insert((Some(vlan), src), incoming);
+    }
+    let known = table.get(&(Some(vlan), dst)).copied();
+    if known == Some(incoming) {
+        return Ok(());
+    }
+    for (port, config) in nics.iter().zip(vlans) {
+        let out = match known {
+            Some(known) => port.id == known,
+            None => port.id != incoming && port.link_id.is_some(),
+        };
+        if out && config.carries(vlan) {
+            let data = config.egress(&frame, vlan);
+            // Tagging may push a full sized frame over the port's mtu.
+            mail.send_or_drop(port, data)?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Node for SwitchNode {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for port in &self.ports {
+            nics.nic(*port, None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            if let Some(vlans) = &self.vlans {
+                let (tag, frame) = untag_vlan(msg.data());
+                let port = nics.iter().position(|nic| nic.id == msg.to());
+                // Frames of a VLAN the port doesn't carry are dropped.
+                if let Some(vlan) = port.and_then(|port| vlans[port].classify(tag)) {
+                    forward_vlan(&mut self.table, mail, nics, vlans, msg.to(), vlan, frame)?;
+                }
+                continue;
+            }
+
+            // Anything that isn't an ethernet frame is dropped.
+            let Ok(frame) = EthernetFrame::new_checked(msg.data()) else {
+                continue;
+            };
+            let (src, dst) = (frame.src_addr(), frame.dst_addr());
+            if src.is_unicast() {
+                self.table.insert((None, src), msg.to());
+            }
+
+            match self.port_of(&dst).and_then(|port| nics.find_id(port)) {
+                Some(port) if port.id == msg.to() => {}
+                Some(port) => {
+                    mail.send_or_drop(port, msg.into_data())?;
+                }
+                _ => {
+                    let incoming = msg.to();
+                    let data = msg.into_data();
+                    for port in nics.iter() {
+                        if port.id != incoming && port.link_id.is_some() {
+                            mail.send_or_drop(port, data.clone())?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_round_trip() {
+        let frame: Vec<u8> = (0..20).collect();
+        let tagged = tag_vlan(&frame, 42);
+        assert_eq!(tagged.len(), 24);
+        assert_eq!(tagged[12..16], [0x81, 0x00, 0, 42]);
+        assert_eq!(untag_vlan(&tagged), (Some(42), frame.clone()));
+        assert_eq!(untag_vlan(&frame), (None, frame));
+    }
+}
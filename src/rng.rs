@@ -0,0 +1,33 @@
+/// The seed used when none is given, so runs are reproducible.
+const DEFAULT_SEED: u64 = 0x5eed_e1f1_5eed_e1f1;
+
+/// A small deterministic pseudo random number generator (splitmix64).
+///
+/// The simulation only needs repeatable randomness, not cryptographic quality.
+/// Nodes can keep their own, such as for `Nics::random_neighbor`, to stay deterministic.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// A generator seeded with `DEFAULT_SEED` offset by `stream`.
+    pub fn new(stream: u64) -> Self {
+        Self {
+            state: DEFAULT_SEED.wrapping_add(stream),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -1,9 +1,36 @@
-use std::collections::HashMap;
+use std::any::Any;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Write;
+use std::future::Future;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
 
-use crate::nics::{LinkId, Nic, NicId, Nics, NicsMut};
+use serde::{Deserialize, Serialize};
+use smoltcp::wire::{EthernetAddress, ETHERNET_HEADER_LEN};
+
+pub mod barrier;
+pub mod event;
+pub mod recorder;
+pub mod report;
+pub mod stats;
+pub mod topology;
+
+use self::event::{DropReason, SimEvent};
+use self::recorder::{Delivery, SimRecorder};
+use self::report::SimReport;
+use self::stats::Stats;
+use crate::message::{IncomingMsg, Mailbox, MsgId, OutgoingMsg};
+use crate::nics::{LinkId, MulticastGroup, Nic, NicError, NicGroup, NicId, Nics, NicsMut};
+use crate::node::{Continue, MessageFilter, NodeError};
+use crate::rng::Rng;
 use crate::{nics::NicAllocator, node::Node};
-use std::sync::mpsc;
-use std::sync::Arc;
 
 /// Calculates the bounds for a slice of nics that correspond with a node.
 fn slice_bounds(nics: &[Nic], node: usize) -> Option<(usize, usize)> {
@@ -17,21 +44,162 @@ fn slice_bounds(nics: &[Nic], node: usize) -> Option<(usize, usize)> {
     Some((start, end))
 }
 
-// pub(crate) struct Simulator {
-//     scheduled: mpsc::Receiver<>,
-// }
+/// Whether both ends of a link may transmit at once.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum LinkDuplex {
+    #[default]
+    Full,
+    /// Frames sent from both ends while either is in flight collide, and both are dropped.
+    Half,
+    /// Frames only go from the link's first nic to its second, which can't send over it.
+    Simplex,
+}
+
+/// When a frame on a bandwidth limited link starts arriving at the other end.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum LinkForwarding {
+    /// Once the whole frame has been sent.
+    #[default]
+    StoreAndForward,
+    /// Once its Ethernet header has been sent.
+    CutThrough,
+}
+
+/// Typical figures for common media, to apply to a link with `Topology::set_profile`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinkProfile {
+    GigabitEthernet,
+    Wifi80211ac,
+    Lte,
+    /// A geostationary satellite hop.
+    Satellite,
+}
+
+impl LinkProfile {
+    /// Nanoseconds a message takes to cross the link in either direction.
+    pub fn latency(self) -> u64 {
+        match self {
+            LinkProfile::GigabitEthernet => 5_000,
+            LinkProfile::Wifi80211ac => 2_000_000,
+            LinkProfile::Lte => 30_000_000,
+            LinkProfile::Satellite => 300_000_000,
+        }
+    }
+
+    /// Bits per second each end can send.
+    pub fn bandwidth(self) -> u64 {
+        match self {
+            LinkProfile::GigabitEthernet => 1_000_000_000,
+            LinkProfile::Wifi80211ac => 433_000_000,
+            LinkProfile::Lte => 20_000_000,
+            LinkProfile::Satellite => 25_000_000,
+        }
+    }
+
+    /// Up to how many nanoseconds each message is randomly delayed by.
+    pub fn jitter(self) -> u64 {
+        match self {
+            LinkProfile::GigabitEthernet | LinkProfile::Satellite => 0,
+            LinkProfile::Wifi80211ac => 1_000_000,
+            LinkProfile::Lte => 5_000_000,
+        }
+    }
+
+    /// The chance of each byte having a bit flipped on the way.
+    pub fn bit_error_rate(self) -> f64 {
+        match self {
+            LinkProfile::GigabitEthernet => 0.0,
+            LinkProfile::Wifi80211ac | LinkProfile::Satellite => 1e-6,
+            LinkProfile::Lte => 1e-7,
+        }
+    }
+}
+
+/// A connection between two nics.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Link {
+    pub(crate) id: LinkId,
+    pub(crate) nics: (NicId, NicId),
+    #[serde(default)]
+    pub(crate) duplex: LinkDuplex,
+    /// How many messages may be in flight on the link at once; more are dropped.
+    #[serde(default)]
+    pub(crate) max_queue: Option<usize>,
+    /// The chance of each delivered byte having one of its bits flipped.
+    #[serde(default)]
+    pub(crate) bit_error_rate: f64,
+    /// Bits per second each end can send. Frames queue behind each other while the sender is busy.
+    /// `None` sends frames instantly.
+    #[serde(default)]
+    pub(crate) bandwidth: Option<u64>,
+    /// Up to how many nanoseconds each message is randomly delayed by, on top of the latency.
+    #[serde(default)]
+    pub(crate) jitter: u64,
+    /// The chance of each message arriving twice, the copy a nanosecond after the original.
+    #[serde(default)]
+    pub(crate) duplicate_probability: f64,
+    /// Nanoseconds from the first nic to the second and back, in place of the nics' latencies.
+    #[serde(default)]
+    pub(crate) asymmetric_latency: Option<(u64, u64)>,
+    #[serde(default)]
+    pub(crate) forwarding: LinkForwarding,
+    /// With a bandwidth limit, the weight of each flow when serving frames in weighted fair order.
+    /// Flows not listed weigh 1. `None` sends frames in the order they were queued.
+    #[serde(default)]
+    pub(crate) flow_weights: Option<Vec<(u32, u32)>>,
+}
+
+impl Link {
+    /// Returns the nic on the other end of the link, if `nic` is one of its endpoints.
+    pub(crate) fn partner(&self, nic: NicId) -> Option<NicId> {
+        match self.nics {
+            (a, b) if a == nic => Some(b),
+            (a, b) if b == nic => Some(a),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq<(NicId, NicId)> for Link {
+    fn eq(&self, other: &(NicId, NicId)) -> bool {
+        self.nics == *other
+    }
+}
+
+/// The serialized form of a `Topology`.
+#[derive(Serialize, Deserialize)]
+struct TopologySnapshot {
+    hardware: Vec<Nic>,
+    names: Vec<String>,
+    links: Vec<Link>,
+    next_link: LinkId,
+    #[serde(default)]
+    memberships: Vec<(NicId, MulticastGroup)>,
+}
 
-pub(crate) struct Topology {
+/// Cloning copies the hardware and links, so one setup can seed many runs.
+/// Mailboxes and anything else a running `Sim` holds are not part of it.
+#[derive(Clone, Debug)]
+pub struct Topology {
     hardware: Vec<Nic>,
-    // Links are full-duplex
-    pub(crate) links: Vec<(NicId, NicId)>,
+    /// `Node::name` of each node, by group.
+    names: Vec<String>,
+    // Links are full-duplex unless set otherwise
+    pub(crate) links: Vec<Link>,
+    /// Link ids are never reused, so a torn down link can't be confused with a new one.
+    next_link: LinkId,
+    /// The multicast groups each nic has joined.
+    memberships: Vec<(NicId, MulticastGroup)>,
 }
 
 impl Topology {
-    fn new(hardware: Vec<Nic>, capacity: usize) -> Self {
+    fn new(hardware: Vec<Nic>, names: Vec<String>, capacity: usize) -> Self {
         Self {
             hardware,
+            names,
             links: Vec::with_capacity(capacity),
+            next_link: 0,
+            memberships: Vec::new(),
         }
     }
 
@@ -55,68 +223,2000 @@ impl Topology {
         &mut self.hardware[start + node..end + node]
     }
 
-    // pub(crate) fn node_slice(&mut self) -> Vec<&mut [Nic]> {
-    //     self.hardware
-    //         .chunk_by_mut(|l, r| l.group == r.group)
-    //         .collect()
-    // }
+    /// The nics of each node, in node order. Nodes without nics are skipped.
+    pub fn node_slices(&self) -> impl Iterator<Item = &[Nic]> {
+        self.hardware.chunk_by(|l, r| l.group == r.group)
+    }
 
     pub(crate) fn all_nics(&self) -> &[Nic] {
         &self.hardware
     }
 
+    /// Where nic `id` is in `hardware`.
+    fn nic_index(&self, id: NicId) -> Option<usize> {
+        // Ids match positions unless the hardware has been rearranged.
+        match self.hardware.get(id as usize) {
+            Some(nic) if nic.id == id => Some(id as usize),
+            _ => self.hardware.iter().position(|nic| nic.id == id),
+        }
+    }
+
+    /// The nic with id `id`.
+    ///
+    /// # Panics!
+    /// If no nic has the id.
+    pub(crate) fn nic(&self, id: NicId) -> &Nic {
+        let index = self.nic_index(id).expect("nic should exist");
+        &self.hardware[index]
+    }
+
+    /// The nic with id `id`.
+    ///
+    /// # Panics!
+    /// If no nic has the id.
+    pub(crate) fn nic_mut(&mut self, id: NicId) -> &mut Nic {
+        let index = self.nic_index(id).expect("nic should exist");
+        &mut self.hardware[index]
+    }
+
+    /// The number of nodes in the topology.
+    pub fn node_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The number of nics across every node in the topology.
+    pub fn nic_count(&self) -> usize {
+        self.hardware.len()
+    }
+
+    /// Append the hardware of a node added after setup.
+    /// Nic ids and groups keep counting from the existing hardware, so node slices stay contiguous.
+    pub(crate) fn add_hardware(&mut self, name: &str, nics: Vec<Nic>) {
+        self.names.push(name.to_string());
+        self.hardware.extend(nics);
+    }
+
+    /// Undo `add_hardware` for the last node, `node`, along with the links made from `first_link` on.
+    /// The links must not have been filled in yet.
+    pub(crate) fn remove_node(&mut self, node: usize, first_link: LinkId) {
+        self.names.truncate(node);
+        self.hardware.retain(|nic| nic.group != node as NicGroup);
+        self.links.retain(|link| link.id < first_link);
+        let hardware = &self.hardware;
+        self.memberships
+            .retain(|(nic, _)| hardware.iter().any(|other| other.id == *nic));
+    }
+
+    pub(crate) fn join_group(&mut self, nic: NicId, group: MulticastGroup) {
+        if !self.is_member(nic, group) {
+            self.memberships.push((nic, group));
+        }
+    }
+
+    /// The nics at either end of each link, in the order the links were made.
+    pub fn links(&self) -> impl Iterator<Item = (NicId, NicId)> + '_ {
+        self.links.iter().map(|link| link.nics)
+    }
+
+    /// The id of the link joining nics `a` and `b`, in either direction, if they are linked to each other.
+    pub fn link_between(&self, a: NicId, b: NicId) -> Option<LinkId> {
+        self.links
+            .iter()
+            .find(|link| link.nics == (a, b) || link.nics == (b, a))
+            .map(|link| link.id)
+    }
+
+    /// The nic on the other end of `nic`'s link, if it is linked.
+    pub fn link_partner(&self, nic: NicId) -> Option<NicId> {
+        self.links.iter().find_map(|link| link.partner(nic))
+    }
+
+    /// Whether `nic` has joined `group`.
+    pub fn is_member(&self, nic: NicId, group: MulticastGroup) -> bool {
+        self.memberships.contains(&(nic, group))
+    }
+
+    /// The sum of the latencies of the link's nics.
+    fn nic_latency(&self, link: &Link) -> u64 {
+        self.nic(link.nics.0).latency.unwrap_or(0) + self.nic(link.nics.1).latency.unwrap_or(0)
+    }
+
+    /// Time for a message to cross the link whichever way it goes: the slower direction if
+    /// `set_asymmetric_latency` overrides them, or else the sum of its nics' latencies.
+    pub(crate) fn link_latency(&self, link: &Link) -> u64 {
+        match link.asymmetric_latency {
+            Some((forward, back)) => forward.max(back),
+            None => self.nic_latency(link),
+        }
+    }
+
+    /// Time for a message sent from `from` to cross the link, which may differ by direction.
+    pub(crate) fn directed_latency(&self, link: &Link, from: NicId) -> u64 {
+        match link.asymmetric_latency {
+            Some((forward, _)) if from == link.nics.0 => forward,
+            Some((_, back)) => back,
+            None => self.nic_latency(link),
+        }
+    }
+
+    /// Serialize the hardware and links, to be restored with `from_json`.
+    pub fn to_json(&self) -> String {
+        let snapshot = TopologySnapshot {
+            hardware: self.hardware.clone(),
+            names: self.names.clone(),
+            links: self.links.clone(),
+            next_link: self.next_link,
+            memberships: self.memberships.clone(),
+        };
+        serde_json::to_string(&snapshot).expect("a topology should always serialize")
+    }
+
+    /// Restore a topology saved with `to_json`, without running any node's `startup`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let snapshot: TopologySnapshot = serde_json::from_str(json)?;
+        Ok(Self {
+            hardware: snapshot.hardware,
+            names: snapshot.names,
+            links: snapshot.links,
+            next_link: snapshot.next_link,
+            memberships: snapshot.memberships,
+        })
+    }
+
+    /// For each node, the nodes it links to and the latency of each link.
+    fn adjacency(&self) -> Vec<Vec<(usize, u64)>> {
+        self.adjacency_by(|link, from| self.directed_latency(link, from))
+    }
+
+    /// For each node, the nodes it links to, weighing each link with `weight` of it and the nic sending over it.
+    fn adjacency_by(&self, weight: impl Fn(&Link, NicId) -> u64) -> Vec<Vec<(usize, u64)>> {
+        let mut adjacency = vec![Vec::new(); self.node_count()];
+        for link in &self.links {
+            let a = self.nic(link.nics.0).group as usize;
+            let b = self.nic(link.nics.1).group as usize;
+            adjacency[a].push((b, weight(link, link.nics.0)));
+            if link.duplex != LinkDuplex::Simplex {
+                adjacency[b].push((a, weight(link, link.nics.1)));
+            }
+        }
+        adjacency
+    }
+
+    /// How many links node `node` has, 0 if it does not exist.
+    pub fn degree(&self, node: usize) -> usize {
+        self.adjacency().get(node).map_or(0, Vec::len)
+    }
+
+    /// Whether every node can reach every other over the links.
+    pub fn is_connected(&self) -> bool {
+        let adjacency = self.adjacency();
+        let mut reached = vec![false; adjacency.len()];
+        let mut frontier = vec![0];
+        while let Some(node) = frontier.pop() {
+            if reached.get(node).is_none_or(|&reached| reached) {
+                continue;
+            }
+            reached[node] = true;
+            frontier.extend(adjacency[node].iter().map(|&(next, _)| next));
+        }
+        reached.iter().all(|&reached| reached)
+    }
+
+    /// Whether the links form a loop, which floods broadcasts forever between bridges without a spanning tree.
+    /// Two links between the same pair of nodes count as a loop.
+    pub fn has_cycle(&self) -> bool {
+        // Union-find over the nodes: a link between two already joined nodes closes a loop.
+        let mut parent: Vec<usize> = (0..self.node_count()).collect();
+        self.links.iter().any(|link| {
+            let a = union_find_root(&mut parent, self.nic(link.nics.0).group as usize);
+            let b = union_find_root(&mut parent, self.nic(link.nics.1).group as usize);
+            parent[a] = b;
+            a == b
+        })
+    }
+
+    /// The nodes along the lowest latency path from node `from` to node `to`, including both.
+    /// Returns `None` if `to` can't be reached.
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        Self::lowest_weight_path(&self.adjacency(), from, to)
+    }
+
+    /// Like `shortest_path`, along the path with the lowest total `Nic::cost` of the nics sent out of.
+    pub fn shortest_path_by_cost(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let adjacency = self.adjacency_by(|_, from| self.nic(from).cost as u64);
+        Self::lowest_weight_path(&adjacency, from, to)
+    }
+
+    /// The nodes along the path from `from` to `to` with the lowest total weight, found with Dijkstra's algorithm.
+    fn lowest_weight_path(
+        adjacency: &[Vec<(usize, u64)>],
+        from: usize,
+        to: usize,
+    ) -> Option<Vec<usize>> {
+        if from >= adjacency.len() || to >= adjacency.len() {
+            return None;
+        }
+        let mut distance = vec![u64::MAX; adjacency.len()];
+        let mut previous = vec![None; adjacency.len()];
+        let mut frontier = BinaryHeap::new();
+        distance[from] = 0;
+        frontier.push(Reverse((0, from)));
+        while let Some(Reverse((dist, node))) = frontier.pop() {
+            if node == to {
+                break;
+            }
+            if dist > distance[node] {
+                continue;
+            }
+            for &(next, weight) in &adjacency[node] {
+                let through = dist + weight;
+                if through < distance[next] {
+                    distance[next] = through;
+                    previous[next] = Some(node);
+                    frontier.push(Reverse((through, next)));
+                }
+            }
+        }
+        if distance[to] == u64::MAX {
+            return None;
+        }
+        let mut path = vec![to];
+        while let Some(node) = previous[*path.last()?] {
+            path.push(node);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The total latency along `path`, taking the fastest link between each pair of nodes.
+    /// Returns `None` if consecutive nodes in the path are not linked.
+    pub fn path_latency(&self, path: &[usize]) -> Option<u64> {
+        let adjacency = self.adjacency();
+        path.windows(2)
+            .map(|hop| {
+                adjacency
+                    .get(hop[0])?
+                    .iter()
+                    .filter(|(next, _)| *next == hop[1])
+                    .map(|(_, latency)| *latency)
+                    .min()
+            })
+            .sum()
+    }
+
+    /// A minimum spanning tree over the links by latency, or a forest if the topology is disconnected.
+    /// Ties go to the link made first.
+    pub fn spanning_tree(&self) -> Vec<(NicId, NicId)> {
+        self.links_where(self.in_spanning_tree(), true)
+    }
+
+    /// The links left out of `spanning_tree`, each of which closes a loop.
+    pub fn blocked_links(&self) -> Vec<(NicId, NicId)> {
+        self.links_where(self.in_spanning_tree(), false)
+    }
+
+    fn links_where(&self, flags: Vec<bool>, want: bool) -> Vec<(NicId, NicId)> {
+        self.links
+            .iter()
+            .zip(flags)
+            .filter(|(_, flag)| *flag == want)
+            .map(|(link, _)| link.nics)
+            .collect()
+    }
+
+    /// Kruskal's algorithm, flagging each link that is part of the spanning tree.
+    fn in_spanning_tree(&self) -> Vec<bool> {
+        let mut order: Vec<usize> = (0..self.links.len()).collect();
+        order.sort_by_key(|&i| (self.link_latency(&self.links[i]), self.links[i].id));
+        let mut parents: Vec<usize> = (0..self.node_count()).collect();
+        let mut in_tree = vec![false; self.links.len()];
+        for i in order {
+            let (a, b) = self.links[i].nics;
+            let a = union_find_root(&mut parents, self.nic(a).group as usize);
+            let b = union_find_root(&mut parents, self.nic(b).group as usize);
+            if a != b {
+                parents[a] = b;
+                in_tree[i] = true;
+            }
+        }
+        in_tree
+    }
+
+    /// Render the topology as a GraphViz DOT graph, with a vertex per node and an edge per link.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph topology {\n");
+        for (node, name) in self.names.iter().enumerate() {
+            let label = name.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(dot, "    {node} [label=\"{label}\"];")
+                .expect("writing to a String should not fail");
+        }
+        for link in self.links.iter() {
+            let a = self.nic(link.nics.0).group;
+            let b = self.nic(link.nics.1).group;
+            let latency = self.link_latency(link);
+            writeln!(dot, "    {a} -- {b} [label=\"{latency}ns\"];")
+                .expect("writing to a String should not fail");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
     pub(crate) fn link_nics(&mut self, nic1: NicId, nic2: NicId) {
-        self.links.push((nic1, nic2));
+        let id = self.next_link;
+        self.next_link += 1;
+        self.links.push(Link {
+            id,
+            nics: (nic1, nic2),
+            duplex: LinkDuplex::Full,
+            max_queue: None,
+            bit_error_rate: 0.0,
+            bandwidth: None,
+            jitter: 0,
+            duplicate_probability: 0.0,
+            asymmetric_latency: None,
+            forwarding: LinkForwarding::StoreAndForward,
+            flow_weights: None,
+        });
     }
 
     /// Call after `Node::startup` has been called for every node in the simulation.
     /// This will complete the `Option<LinkId>` field for each `Nic`.
     pub(crate) fn fill_links(&mut self) {
-        for (link, nics) in self.links.iter().enumerate() {
-            self.hardware[nics.0 as usize].link(link as u64);
-            self.hardware[nics.1 as usize].link(link as u64);
+        let index: HashMap<NicId, usize> = self
+            .hardware
+            .iter()
+            .enumerate()
+            .map(|(i, nic)| (nic.id, i))
+            .collect();
+        for link in self.links.iter() {
+            for (nic, partner) in [link.nics, (link.nics.1, link.nics.0)] {
+                let i = *index.get(&nic).expect("linked nics should exist");
+                let partner =
+                    self.hardware[*index.get(&partner).expect("linked nics should exist")].clone();
+                self.hardware[i].link(link.id, &partner);
+            }
+            if link.duplex == LinkDuplex::Simplex {
+                let i = *index.get(&link.nics.1).expect("linked nics should exist");
+                self.hardware[i].receive_only = true;
+            }
+        }
+    }
+
+    /// Tear down every link and multicast membership, returning the links, so startup can be run again.
+    pub(crate) fn clear_links(&mut self) -> Vec<Link> {
+        for nic in &mut self.hardware {
+            nic.unlink();
+        }
+        self.next_link = 0;
+        self.memberships.clear();
+        mem::take(&mut self.links)
+    }
+
+    /// Bring back the links of `built` whose nics are both free again, with their settings.
+    /// Call `fill_links` afterwards.
+    pub(crate) fn relink_missing(&mut self, built: &[Link]) {
+        for link in built {
+            let (a, b) = link.nics;
+            let taken = self.links.iter().any(|existing| {
+                [a, b].contains(&existing.nics.0) || [a, b].contains(&existing.nics.1)
+            });
+            if !taken {
+                let id = self.next_link;
+                self.next_link += 1;
+                self.links.push(Link { id, ..link.clone() });
+            }
+        }
+    }
+
+    /// Carry the settings of `old` links over to links between the same nics.
+    pub(crate) fn restore_links(&mut self, old: Vec<Link>) {
+        for link in &mut self.links {
+            if let Some(previous) = old.iter().find(|previous| previous.nics == link.nics) {
+                *link = Link {
+                    id: link.id,
+                    ..previous.clone()
+                };
+            }
+        }
+    }
+
+    pub(crate) fn link(&self, link: LinkId) -> Option<&Link> {
+        self.links.iter().find(|l| l.id == link)
+    }
+
+    /// Make a link full, half-duplex or simplex. Returns `false` if the link does not exist.
+    pub fn set_duplex(&mut self, link: LinkId, duplex: LinkDuplex) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.duplex = duplex;
+                let far_end = link.nics.1;
+                self.nic_mut(far_end).receive_only = duplex == LinkDuplex::Simplex;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Link `from` to `to` so frames only go from `from` to `to`. Sending from `to` fails with `NodeError::LinkDown`.
+    ///
+    /// Fails with `NicError::NeighborNotFound` if either nic does not exist,
+    /// `NicError::AlreadyLinked` if either is already part of a link, or `NicError::SelfLink` if they are the same.
+    pub fn link_simplex(&mut self, from: NicId, to: NicId) -> Result<LinkId, NicError> {
+        if from == to {
+            return Err(NicError::SelfLink);
+        }
+        for nic in [from, to] {
+            let index = self.nic_index(nic).ok_or(NicError::NeighborNotFound)?;
+            if self.hardware[index].link_id.is_some() {
+                return Err(NicError::AlreadyLinked);
+            }
+        }
+        let id = self.next_link;
+        self.link_nics(from, to);
+        let (from_nic, to_nic) = (self.nic(from).clone(), self.nic(to).clone());
+        self.nic_mut(from).link(id, &to_nic);
+        self.nic_mut(to).link(id, &from_nic);
+        self.set_duplex(id, LinkDuplex::Simplex);
+        Ok(id)
+    }
+
+    /// Limit how many messages may be in flight on a link, dropping any sent while it is full.
+    /// `None` removes the limit. Returns `false` if the link does not exist.
+    pub fn set_max_queue(&mut self, link: LinkId, max_queue: Option<usize>) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.max_queue = max_queue;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Corrupt messages on a link: each byte has a `rate` chance of one bit being flipped on delivery.
+    /// Returns `false` if the link does not exist.
+    pub fn set_bit_error_rate(&mut self, link: LinkId, rate: f64) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.bit_error_rate = rate;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Limit a link to `bandwidth` bits per second in each direction, or `None` for no limit.
+    /// Returns `false` if the link does not exist.
+    pub fn set_bandwidth(&mut self, link: LinkId, bandwidth: Option<u64>) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.bandwidth = bandwidth;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Share a bandwidth limited link between flows in proportion to their weights, as `(flow, weight)` pairs,
+    /// instead of sending frames in the order they were queued. Flows come from `Mailbox::send_with_flow`,
+    /// and those not listed weigh 1. `None` goes back to first in, first out.
+    /// Returns `false` if the link does not exist.
+    pub fn set_flow_weights(&mut self, link: LinkId, weights: Option<Vec<(u32, u32)>>) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.flow_weights = weights;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Choose whether frames on a bandwidth limited link arrive after being sent in full, or after their header.
+    /// Returns `false` if the link does not exist.
+    pub fn set_forwarding(&mut self, link: LinkId, forwarding: LinkForwarding) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.forwarding = forwarding;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Give a link a different latency each way: `forward` nanoseconds from its first nic to its second,
+    /// `back` the other way, or `None` to use the nics' latencies. Returns `false` if the link does not exist.
+    pub fn set_asymmetric_latency(&mut self, link: LinkId, latency: Option<(u64, u64)>) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.asymmetric_latency = latency;
+                true
+            }
+            None => false,
         }
     }
+
+    /// Delay each message on a link by a random extra `0..=jitter` nanoseconds, so they may arrive out of order.
+    /// Returns `false` if the link does not exist.
+    pub fn set_jitter(&mut self, link: LinkId, jitter: u64) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.jitter = jitter;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Give a link the latency, bandwidth, jitter and bit error rate of `profile`, in place of the nics' latencies.
+    /// Returns `false` if the link does not exist.
+    pub fn set_profile(&mut self, link: LinkId, profile: LinkProfile) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.asymmetric_latency = Some((profile.latency(), profile.latency()));
+                link.bandwidth = Some(profile.bandwidth());
+                link.jitter = profile.jitter();
+                link.bit_error_rate = profile.bit_error_rate();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Deliver each message on a link a second time with probability `probability`.
+    /// Returns `false` if the link does not exist.
+    pub fn set_duplicate_probability(&mut self, link: LinkId, probability: f64) -> bool {
+        match self.links.iter_mut().find(|l| l.id == link) {
+            Some(link) => {
+                link.duplicate_probability = probability;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Change what sending out of a nic costs. Returns `false` if the nic does not exist.
+    pub fn set_cost(&mut self, nic: NicId, cost: u32) -> bool {
+        match self.nic_index(nic) {
+            Some(index) => {
+                self.hardware[index].cost = cost;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Bring a nic up or down, keeping its link. Returns `false` if the nic does not exist.
+    pub fn set_enabled(&mut self, nic: NicId, enabled: bool) -> bool {
+        match self.nic_index(nic) {
+            Some(index) => {
+                self.hardware[index].enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The first nic of a node that isn't part of any link.
+    pub(crate) fn free_nic(&self, node: usize) -> Option<NicId> {
+        self.nics(node)
+            .iter()
+            .map(|nic| nic.id)
+            .find(|&nic| self.links.iter().all(|link| link.partner(nic).is_none()))
+    }
+
+    /// Tear down a link, clearing the `link_id` of both of its nics.
+    /// Messages still in flight over the link will be dropped.
+    pub fn unlink(&mut self, link: LinkId) -> Option<Link> {
+        let position = self.links.iter().position(|l| l.id == link)?;
+        let removed = self.links.remove(position);
+        self.nic_mut(removed.nics.0).unlink();
+        self.nic_mut(removed.nics.1).unlink();
+        Some(removed)
+    }
 }
 
 #[derive(Debug)]
 pub enum SimErr {
-    /// No Nics were initialized for at least one node in the simulation.
-    NodeNoHardware,
+    /// A node in the simulation did not initialize any Nics.
+    NodeNoHardware { node: usize, name: String },
+    /// A node's `process` returned an error.
+    NodeFailed { node: usize, error: NodeError },
+    /// A generated topology needed another link on a node whose nics are all in use.
+    NoFreeNic { node: usize },
+    /// A `TopologyBuilder` connection named a node that was never declared.
+    UnknownNode { name: String },
+    /// A node tried to link to a mac that no nic in the simulation has.
+    UnresolvedNeighbor { node: usize, mac: EthernetAddress },
+    /// A node's `try_startup` returned an error.
+    StartupFailed { node: usize, error: NicError },
+    /// A node's `process` panicked. The node is never polled again, but the rest can keep running.
+    NodePanicked { node: usize, message: String },
+    /// `Sim::reset` could not get a node back from its process, so the simulation cannot go on.
+    NodeLost { node: usize },
+    /// More events than the instant limit came due at virtual time `at`, so the clock could not advance,
+    /// such as when nodes bounce messages back and forth over links with no latency.
+    Stalled { at: u64 },
 }
 
 pub(crate) fn sim_setup(nodes: &mut [&mut dyn Node]) -> Result<Topology, SimErr> {
     let mut nic_allocator = NicAllocator::with_capacity(nodes.len());
+    let mut names = Vec::with_capacity(nodes.len());
     // Generate the hardware for each node.
     for node in nodes.iter_mut() {
         node.hardware(&mut nic_allocator);
-        nic_allocator.next_node()?;
+        nic_allocator.next_node(node.name())?;
+        names.push(node.name().to_string());
     }
-    // nics should never change size after this point
-    let nics = nic_allocator.to_vec();
+    // Every link joins two nics
+    let capacity = nic_allocator.total() / 2;
+    // nics only grow from here if nodes are added to a running `Sim`
+    let nics = nic_allocator.into_vec();
 
-    let mut topology = Topology::new(nics, nodes.len());
+    let mut topology = Topology::new(nics, names, capacity);
 
     // Run startup for each node.
     for (i, node) in nodes.iter_mut().enumerate() {
         // The rust compiler hates it when hardware and topology are mutable references, so NicsMut have to own them.
         let mut nics_mut = NicsMut::from_slice(i, &mut topology);
-        node.startup(&mut nics_mut);
+        nics_mut.startup(&mut **node)?;
     }
     topology.fill_links();
     Ok(topology)
 }
 
-pub fn run_sim(nodes: &mut [&mut dyn Node]) -> Result<(), SimErr> {
-    let topology = sim_setup(nodes)?;
+enum Event {
+    /// A message travelling over a link.
+    Deliver {
+        link: LinkId,
+        msg: IncomingMsg,
+        sent_at: u64,
+    },
+    /// A node asked to be woken, such as by `Mailbox::recv_timeout`.
+    Wake { node: usize },
+    /// A recorded or injected message, delivered straight to its nic.
+    Replay { msg: IncomingMsg, sent_at: u64 },
+    /// A nic on a weighted fair link is free to send the next of its queued frames.
+    Dequeue { nic: NicId },
+}
+
+/// A frame bound for a link, which may first wait its turn on a weighted fair one.
+struct PendingFrame {
+    node: usize,
+    out: OutgoingMsg,
+    to: NicId,
+    link: LinkId,
+    latency: u64,
+    /// When the node sent the frame, before any wait on a weighted fair queue.
+    sent_at: u64,
+    /// Frames are sent in order of their finish tag, lowest first.
+    finish: u64,
+}
+
+/// The frames waiting to leave one nic of a weighted fair link.
+#[derive(Default)]
+struct FairQueue {
+    /// In the order they were queued, which breaks ties between equal finish tags.
+    frames: Vec<PendingFrame>,
+    /// The finish tag of the last frame queued from each flow.
+    last_finish: HashMap<u32, u64>,
+    /// The finish tag of the frame last sent, which new flows start from.
+    virtual_time: u64,
+    /// Whether an `Event::Dequeue` is due for the nic.
+    serving: bool,
+}
 
-    Ok(())
+/// An event due at `at`.
+struct Scheduled {
+    at: u64,
+    /// Breaks ties between events at the same time, in the order they were scheduled.
+    seq: u64,
+    event: Event,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+/// Flags a node to be polled again.
+#[derive(Default)]
+struct NodeWaker {
+    woken: AtomicBool,
+}
+
+impl Wake for NodeWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+/// A node either borrowed from the caller, or handed over to the simulation.
+enum NodeHandle<'a> {
+    Borrowed(&'a mut dyn Node),
+    Owned(Box<dyn Node + 'a>),
+}
+
+impl<'a> Deref for NodeHandle<'a> {
+    type Target = dyn Node + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            NodeHandle::Borrowed(node) => *node,
+            NodeHandle::Owned(node) => node.as_ref(),
+        }
+    }
+}
+
+impl DerefMut for NodeHandle<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            NodeHandle::Borrowed(node) => *node,
+            NodeHandle::Owned(node) => node.as_mut(),
+        }
+    }
+}
+
+type Process<'a> =
+    Pin<Box<dyn Future<Output = (NodeHandle<'a>, Result<Continue, NodeError>)> + 'a>>;
+
+type EventHook<'a> = Box<dyn FnMut(&SimEvent) + 'a>;
+
+/// A message on a half-duplex link that has yet to arrive.
+struct InFlight {
+    /// The `Scheduled::seq` of its delivery.
+    seq: u64,
+    link: LinkId,
+    from: NicId,
+    collided: bool,
+}
+
+enum Task<'a> {
+    /// The node is waiting for mail.
+    Idle(NodeHandle<'a>),
+    /// The node's `process` has not yet returned.
+    Running(Process<'a>),
+    /// The node's `process` returned `Continue::Done`.
+    Done(NodeHandle<'a>),
+}
+
+/// Holds a node for its running `process`, handing it back to `slot` if the process is dropped unfinished.
+struct Lent<'a> {
+    node: Option<NodeHandle<'a>>,
+    slot: Arc<Mutex<Option<NodeHandle<'a>>>>,
+}
+
+impl Drop for Lent<'_> {
+    fn drop(&mut self) {
+        if let Some(node) = self.node.take() {
+            if let Ok(mut slot) = self.slot.lock() {
+                *slot = Some(node);
+            }
+        }
+    }
+}
+
+/// Which nodes `Sim` polls after each event.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SchedulerPolicy {
+    /// Only nodes with new mail or a wakeup, in index order.
+    #[default]
+    EventDriven,
+    /// Every node that isn't done, in index order, whether or not anything happened to it.
+    /// Idle nodes have `process` called again, so polling order never depends on mailbox state.
+    RoundRobin,
+}
+
+/// Drives each node's `process` on a virtual clock, delivering messages over the topology's links.
+pub struct Sim<'a> {
+    pub(crate) topology: Topology,
+    tasks: Vec<Option<Task<'a>>>,
+    pub(crate) mailboxes: Vec<Mailbox>,
+    wakers: Vec<Arc<NodeWaker>>,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    /// Virtual time, in nanoseconds.
+    now: u64,
+    /// Events handled so far at `now`, checked against `instant_limit`.
+    instant_events: usize,
+    /// How many events may come due at one instant before the run fails with `SimErr::Stalled`.
+    instant_limit: usize,
+    seq: u64,
+    started: bool,
+    /// Set by `pause`, so no further events are processed until `resume`.
+    paused: bool,
+    /// Set while nodes are being polled, so a pass cut short by an error is finished later.
+    interrupted: bool,
+    stats: Stats,
+    on_event: Option<EventHook<'a>>,
+    in_flight: Vec<InFlight>,
+    /// Messages scheduled but not yet delivered, by link.
+    queued: HashMap<LinkId, usize>,
+    /// Frames waiting to leave nics on weighted fair links.
+    fair_queues: HashMap<NicId, FairQueue>,
+    /// The id the next frame sent is given.
+    next_msg_id: u64,
+    recorder: Option<SimRecorder>,
+    /// Messages sent by nodes are discarded, since they are being fed recorded ones.
+    replaying: bool,
+    /// Drives bit errors, so a corrupted run can be repeated.
+    rng: Rng,
+    scheduler: SchedulerPolicy,
+    /// How many messages a node may take from its mailbox each time it is polled.
+    process_budget: Option<usize>,
+    /// Each node's `Node::processing_delay`.
+    processing_delays: Vec<u64>,
+    /// Each node's `Node::filter`, kept apart so it can be consulted while `process` has the node.
+    filters: Vec<Option<MessageFilter>>,
+    /// When each node last had a message delivered.
+    last_delivery: Vec<Option<u64>>,
+    /// Links that went up or down for each node, by local nic, for `Node::on_link_change`.
+    link_changes: Vec<Vec<(NicId, bool)>>,
+    /// Messages each run starts with, from `with_initial_messages`.
+    initial_messages: Vec<IncomingMsg>,
+    /// The links the simulation was built with, for `reset` to bring back those `startup` does not make.
+    built_links: Vec<Link>,
+    /// Where each node lands if its `process` is dropped before returning, for `reset`.
+    reclaimed: Vec<Arc<Mutex<Option<NodeHandle<'a>>>>>,
+}
+
+impl<'a> Sim<'a> {
+    /// Run `nodes` over a topology built for them, such as by `sim_setup` or a `topology` generator.
+    pub fn new(nodes: &'a mut [&mut dyn Node], topology: Topology) -> Self {
+        let handles = nodes
+            .iter_mut()
+            .map(|node| NodeHandle::Borrowed(&mut **node))
+            .collect();
+        Self::from_handles(handles, topology)
+    }
+
+    /// Run nodes owned by the simulation.
+    pub(crate) fn from_owned(nodes: Vec<Box<dyn Node + 'a>>, topology: Topology) -> Self {
+        let handles = nodes.into_iter().map(NodeHandle::Owned).collect();
+        Self::from_handles(handles, topology)
+    }
+
+    fn from_handles(handles: Vec<NodeHandle<'a>>, topology: Topology) -> Self {
+        let count = handles.len();
+        let processing_delays = handles
+            .iter()
+            .map(|handle| handle.processing_delay())
+            .collect();
+        let filters = handles.iter().map(|handle| handle.filter()).collect();
+        let built_links = topology.links.clone();
+        Self {
+            topology,
+            tasks: handles
+                .into_iter()
+                .map(|handle| Some(Task::Idle(handle)))
+                .collect(),
+            mailboxes: (0..count).map(|_| Mailbox::new()).collect(),
+            wakers: (0..count).map(|_| Arc::default()).collect(),
+            queue: BinaryHeap::new(),
+            now: 0,
+            instant_events: 0,
+            instant_limit: DEFAULT_INSTANT_LIMIT,
+            seq: 0,
+            started: false,
+            paused: false,
+            interrupted: false,
+            stats: Stats::default(),
+            on_event: None,
+            in_flight: Vec::new(),
+            queued: HashMap::new(),
+            fair_queues: HashMap::new(),
+            next_msg_id: 1,
+            recorder: None,
+            replaying: false,
+            rng: Rng::new(0),
+            scheduler: SchedulerPolicy::default(),
+            process_budget: None,
+            processing_delays,
+            filters,
+            last_delivery: vec![None; count],
+            link_changes: vec![Vec::new(); count],
+            initial_messages: Vec::new(),
+            built_links,
+            reclaimed: (0..count).map(|_| Arc::default()).collect(),
+        }
+    }
+
+    /// Set up the hardware and links of `nodes`, ready to run.
+    pub fn build(nodes: &'a mut [&mut dyn Node]) -> Result<Self, SimErr> {
+        let topology = sim_setup(nodes)?;
+        Ok(Self::new(nodes, topology))
+    }
+
+    /// Reseed the generator behind random link behaviour such as bit errors and jitter.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Choose which nodes are polled after each event, `SchedulerPolicy::EventDriven` by default.
+    pub fn with_scheduler(mut self, policy: SchedulerPolicy) -> Self {
+        self.scheduler = policy;
+        self
+    }
+
+    /// Let each node take at most `messages` messages from its mailbox each time it is polled, or any number with `None`,
+    /// the default. A node that reaches the limit sees its mailbox as empty until the other nodes have had
+    /// their turn at the same instant, so one busy node can't hold them up.
+    pub fn with_process_budget(mut self, messages: Option<usize>) -> Self {
+        self.process_budget = messages;
+        self
+    }
+
+    /// Fail the run with `SimErr::Stalled` once more than `events` events come due at one instant,
+    /// `DEFAULT_INSTANT_LIMIT` by default.
+    pub fn with_instant_limit(mut self, events: usize) -> Self {
+        self.instant_limit = events;
+        self
+    }
+
+    /// Start node `node` with `messages` in its mailbox, delivered in order at time 0 before any peer sends,
+    /// and again after each `reset`. Messages not addressed to one of its nics arrive on its first one.
+    ///
+    /// # Panics!
+    /// If `node` is not in the simulation, or has no nics while a message is addressed elsewhere.
+    pub fn with_initial_messages(mut self, node: usize, messages: Vec<IncomingMsg>) -> Self {
+        let own: Vec<NicId> = self.topology.nics(node).iter().map(|nic| nic.id).collect();
+        for msg in messages {
+            let msg = if own.contains(&msg.to()) {
+                msg
+            } else {
+                msg.with_to(own[0])
+            };
+            self.seed_message(msg.clone());
+            self.initial_messages.push(msg);
+        }
+        self
+    }
+
+    /// Schedule `msg` for delivery at time 0, giving it an id unless it has one.
+    fn seed_message(&mut self, msg: IncomingMsg) {
+        let msg = match msg.id() {
+            MsgId(0) => msg.with_id(self.msg_id()),
+            _ => msg,
+        };
+        self.schedule(0, Event::Replay { msg, sent_at: 0 });
+    }
+
+    /// Stop processing events, keeping everything queued, until `resume` is called.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Carry on processing events after a `pause`.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Virtual time, in nanoseconds.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    /// Message counters collected so far.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// A summary of the counters collected so far, with the virtual time.
+    pub fn report(&self) -> SimReport {
+        let nodes = (0..self.topology.node_count())
+            .map(|node| (self.topology.names[node].clone(), self.stats.node(node)))
+            .collect();
+        SimReport::new(self.now, nodes, |reason| self.stats.drops(reason))
+    }
+
+    /// Call `hook` with every `SimEvent` as it happens.
+    pub fn on_event(&mut self, hook: impl FnMut(&SimEvent) + 'a) {
+        self.on_event = Some(Box::new(hook));
+    }
+
+    fn emit(&mut self, event: SimEvent) {
+        if let Some(hook) = self.on_event.as_mut() {
+            hook(&event);
+        }
+    }
+
+    /// Record every delivery from now on, to be replayed with `recorder::replay`.
+    pub fn record(&mut self) {
+        self.recorder.get_or_insert_with(SimRecorder::default);
+    }
+
+    /// The deliveries recorded so far, if recording.
+    pub fn recorder(&self) -> Option<&SimRecorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Stop recording, returning the deliveries recorded so far.
+    pub fn take_recorder(&mut self) -> Option<SimRecorder> {
+        self.recorder.take()
+    }
+
+    /// Deliver `data` to node `to_node`'s first nic at the current time, as if nic `from` had sent it,
+    /// so a node can be tested without a peer. `from` needn't be in the simulation.
+    ///
+    /// # Panics!
+    /// If `to_node` is not in the simulation.
+    pub fn inject(&mut self, to_node: usize, from: NicId, data: Vec<u8>) {
+        let to = self.topology.nics(to_node)[0].id;
+        let msg = IncomingMsg::new(from, to, data).with_id(self.msg_id());
+        let sent_at = self.now;
+        self.schedule(self.now, Event::Replay { msg, sent_at });
+    }
+
+    /// Bring a nic up or down while the simulation runs, keeping its link.
+    /// Frames that reach a disabled nic are dropped. Returns `false` if the nic does not exist.
+    pub fn set_enabled(&mut self, nic: NicId, enabled: bool) -> bool {
+        let Some(index) = self.topology.nic_index(nic) else {
+            return false;
+        };
+        let was_enabled = self.topology.hardware[index].enabled;
+        self.topology.set_enabled(nic, enabled);
+        // The link is only up while both ends are.
+        if let Some(partner) = self.topology.link_partner(nic) {
+            if was_enabled != enabled && self.topology.nic(partner).enabled {
+                self.link_changed((nic, partner), enabled);
+            }
+        }
+        true
+    }
+
+    /// Tear down a link, dropping any messages still in flight over it.
+    pub fn unlink(&mut self, link: LinkId) -> Option<Link> {
+        let removed = self.topology.unlink(link)?;
+        self.refresh_sendable(removed.nics);
+        self.emit(SimEvent::LinkDown { link, at: self.now });
+        self.link_changed(removed.nics, false);
+        Some(removed)
+    }
+
+    /// Tell the nodes at both ends of a link whether their nic can send now, since a running `process`
+    /// still has the `Nics` it was given.
+    fn refresh_sendable(&self, nics: (NicId, NicId)) {
+        for nic in [nics.0, nics.1] {
+            if let Some(index) = self.topology.nic_index(nic) {
+                let nic = &self.topology.hardware[index];
+                if let Some(mailbox) = self.mailboxes.get(nic.group as usize) {
+                    mailbox.set_sendable(nic.id, nic.link_id.is_some() && !nic.receive_only);
+                }
+            }
+        }
+    }
+
+    /// Tell the nodes at both ends of a link, by nic, that it went up or down, for `Node::on_link_change`.
+    fn link_changed(&mut self, nics: (NicId, NicId), up: bool) {
+        for nic in [nics.0, nics.1] {
+            if let Some(index) = self.topology.nic_index(nic) {
+                let node = self.topology.hardware[index].group as usize;
+                if !matches!(self.tasks[node], Some(Task::Done(_))) {
+                    self.link_changes[node].push((nic, up));
+                }
+            }
+        }
+    }
+
+    /// Add a node to the simulation, returning its index.
+    ///
+    /// The node's hardware is allocated and its `startup` is run against the current topology,
+    /// so it may link to any existing node. If the simulation has started, its `process`
+    /// is called straight away.
+    pub fn add_node(&mut self, node: Box<dyn Node + 'a>) -> Result<usize, SimErr> {
+        let index = self.tasks.len();
+        let all_nics = self.topology.all_nics();
+        let mut nic_allocator = NicAllocator::starting_from(index as NicGroup, all_nics);
+        node.hardware(&mut nic_allocator);
+        nic_allocator.next_node(node.name())?;
+        self.topology
+            .add_hardware(node.name(), nic_allocator.into_vec());
+
+        let mut node = NodeHandle::Owned(node);
+        let first_link = self.topology.next_link;
+        let mut nics_mut = NicsMut::from_slice(index, &mut self.topology);
+        if let Err(err) = nics_mut.startup(&mut *node) {
+            self.topology.remove_node(index, first_link);
+            return Err(err);
+        }
+        self.topology.fill_links();
+        if self.started {
+            self.links_up(first_link);
+            // Nodes already running may now send over the new links.
+            for link in self
+                .topology
+                .links
+                .iter()
+                .filter(|link| link.id >= first_link)
+            {
+                self.refresh_sendable(link.nics);
+            }
+        }
+
+        self.mailboxes.push(Mailbox::new());
+        self.wakers.push(Arc::default());
+        self.reclaimed.push(Arc::default());
+        self.processing_delays.push(node.processing_delay());
+        self.filters.push(node.filter());
+        self.last_delivery.push(None);
+        self.link_changes.push(Vec::new());
+        if self.started {
+            self.tasks
+                .push(Some(Task::Running(self.spawn(index, node))));
+            self.wakers[index].woken.store(true, Ordering::SeqCst);
+            self.poll_nodes()?;
+        } else {
+            self.tasks.push(Some(Task::Idle(node)));
+        }
+        Ok(index)
+    }
+
+    /// Go back to time 0 for another trial over the same hardware, as if just built.
+    ///
+    /// Every message, timer and counter is cleared, and each node has `Node::reset` then `startup`
+    /// called again. Nodes relink as their `startup` says, and the links the simulation was built with
+    /// come back too, such as those of a `topology` generator. Links between the same nics as before
+    /// keep their settings. The random generator carries on, so reseed with `seed` to repeat a trial.
+    pub fn reset(&mut self) -> Result<(), SimErr> {
+        let mut handles = Vec::with_capacity(self.tasks.len());
+        for node in 0..self.tasks.len() {
+            let handle = match self.tasks[node].take() {
+                Some(Task::Idle(handle) | Task::Done(handle)) => Some(handle),
+                // Dropping the process hands its node back.
+                Some(Task::Running(process)) => {
+                    drop(process);
+                    self.take_reclaimed(node)
+                }
+                // Its process panicked, and was dropped then.
+                None => self.take_reclaimed(node),
+            };
+            handles.push(handle);
+        }
+        if let Some(lost) = handles.iter().position(Option::is_none) {
+            // Leave the nodes that are still here idle, rather than lose them too.
+            for (node, handle) in handles.into_iter().enumerate() {
+                self.tasks[node] = handle.map(Task::Idle);
+            }
+            return Err(SimErr::NodeLost { node: lost });
+        }
+        let mut nodes: Vec<NodeHandle<'a>> = handles.into_iter().flatten().collect();
+
+        let old_links = self.topology.clear_links();
+        for (index, node) in nodes.iter_mut().enumerate() {
+            node.reset();
+            let mut nics_mut = NicsMut::from_slice(index, &mut self.topology);
+            nics_mut.startup(&mut **node)?;
+        }
+        self.topology.relink_missing(&self.built_links);
+        self.topology.fill_links();
+        self.topology.restore_links(old_links);
+        self.filters = nodes.iter().map(|node| node.filter()).collect();
+
+        let count = nodes.len();
+        self.tasks = nodes
+            .into_iter()
+            .map(|handle| Some(Task::Idle(handle)))
+            .collect();
+        self.mailboxes = (0..count).map(|_| Mailbox::new()).collect();
+        self.wakers = (0..count).map(|_| Arc::default()).collect();
+        self.queue.clear();
+        self.now = 0;
+        self.instant_events = 0;
+        self.seq = 0;
+        self.started = false;
+        self.paused = false;
+        self.interrupted = false;
+        self.stats = Stats::default();
+        self.in_flight.clear();
+        self.queued.clear();
+        self.fair_queues.clear();
+        self.next_msg_id = 1;
+        self.last_delivery = vec![None; count];
+        self.link_changes = vec![Vec::new(); count];
+        for msg in self.initial_messages.clone() {
+            self.seed_message(msg);
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            *recorder = SimRecorder::default();
+        }
+        self.replaying = false;
+        Ok(())
+    }
+
+    /// The node its dropped process handed back.
+    fn take_reclaimed(&self, node: usize) -> Option<NodeHandle<'a>> {
+        self.reclaimed[node]
+            .lock()
+            .expect("a node's slot should not be poisoned")
+            .take()
+    }
+
+    /// Run until no messages are left to deliver, or the sim is paused.
+    pub fn run(&mut self) -> Result<(), SimErr> {
+        while !self.flush()?.is_empty() {}
+        Ok(())
+    }
+
+    /// Run until no messages are left to deliver, or the next one is due after `max_time`.
+    /// Events past `max_time` stay queued, so the clock never advances beyond it. Fails with
+    /// `SimErr::Stalled` if the clock stops advancing instead.
+    pub fn run_until(&mut self, max_time: u64) -> Result<(), SimErr> {
+        if !self.started {
+            self.start()?;
+        }
+        self.catch_up()?;
+        while !self.paused
+            && self
+                .queue
+                .peek()
+                .is_some_and(|Reverse(next)| next.at <= max_time)
+        {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Like `run_until`, sleeping before each event so every nanosecond of virtual time
+    /// takes `scale` nanoseconds of wall-clock time. Delivery is unchanged, only paced.
+    pub fn run_realtime(&mut self, max_time: u64, scale: f64) -> Result<(), SimErr> {
+        if !self.started {
+            self.start()?;
+        }
+        self.catch_up()?;
+        let wall_start = Instant::now();
+        let virtual_start = self.now;
+        while let Some(Reverse(next)) = self.queue.peek() {
+            if self.paused || next.at > max_time {
+                break;
+            }
+            let due = (next.at - virtual_start) as f64 * scale;
+            let due = Duration::try_from_secs_f64(due / 1e9).unwrap_or(Duration::MAX);
+            if let Some(wait) = due.checked_sub(wall_start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Step until `predicate` matches an event, returning that event and leaving the sim paused.
+    /// Returns `None` if nothing is left to process before it matches.
+    pub fn run_until_event(
+        &mut self,
+        predicate: impl Fn(&SimEvent) -> bool,
+    ) -> Result<Option<SimEvent>, SimErr> {
+        self.resume();
+        while let Some(event) = self.step()? {
+            if predicate(&event) {
+                self.pause();
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Process the next scheduled event and let nodes react to it, returning the event.
+    /// Returns `None` once there is nothing left to process, or while paused.
+    pub fn step(&mut self) -> Result<Option<SimEvent>, SimErr> {
+        if !self.started {
+            self.start()?;
+        }
+        if self.paused {
+            return Ok(None);
+        }
+        self.catch_up()?;
+        let Some(Reverse(next)) = self.queue.pop() else {
+            return Ok(None);
+        };
+        let event = self.handle(next);
+        self.check_stalled()?;
+        self.poll_nodes()?;
+        Ok(Some(event))
+    }
+
+    /// Process every event due at the next scheduled time, then let nodes react to all of them at once,
+    /// so no node sees part of an instant. Returns the events, none once there is nothing left or while paused.
+    pub fn flush(&mut self) -> Result<Vec<SimEvent>, SimErr> {
+        if !self.started {
+            self.start()?;
+        }
+        let mut events = Vec::new();
+        if self.paused {
+            return Ok(events);
+        }
+        self.catch_up()?;
+        let Some(at) = self.queue.peek().map(|Reverse(next)| next.at) else {
+            return Ok(events);
+        };
+        while let Some(Reverse(next)) = self.queue.peek() {
+            if next.at != at {
+                break;
+            }
+            let Some(Reverse(next)) = self.queue.pop() else {
+                break;
+            };
+            events.push(self.handle(next));
+            self.check_stalled()?;
+        }
+        self.poll_nodes()?;
+        Ok(events)
+    }
+
+    /// Fail once more events than the instant limit have been handled at `now`.
+    fn check_stalled(&self) -> Result<(), SimErr> {
+        if self.instant_events > self.instant_limit {
+            return Err(SimErr::Stalled { at: self.now });
+        }
+        Ok(())
+    }
+
+    /// Advance the clock to `next` and carry it out, without polling nodes.
+    fn handle(&mut self, next: Scheduled) -> SimEvent {
+        if next.at != self.now {
+            self.instant_events = 0;
+        }
+        self.instant_events += 1;
+        self.now = next.at;
+        let event = match next.event {
+            Event::Deliver { link, msg, sent_at } => {
+                let collided = self.land(next.seq);
+                let sender = self.topology.nic(msg.from()).group as usize;
+                self.mailboxes[sender].landed(msg.from(), msg.data().len());
+                if let Some(queued) = self.queued.get_mut(&link) {
+                    *queued -= 1;
+                }
+                // The link may have been torn down while the message was in flight.
+                let dropped = if self.topology.link(link).is_none() {
+                    Some(DropReason::LinkDown)
+                } else if collided {
+                    Some(DropReason::Collision)
+                } else if !self.topology.nic(msg.to()).enabled {
+                    Some(DropReason::NicDisabled)
+                } else if !self.accepts(&msg) {
+                    Some(DropReason::Filtered)
+                } else {
+                    None
+                };
+                if let Some(reason) = dropped {
+                    self.stats.dropped(sender, Some(link), reason);
+                    match reason {
+                        DropReason::Collision => self.stats.collided(sender, link),
+                        DropReason::Filtered => self.stats.filtered(sender, link),
+                        _ => {}
+                    }
+                    SimEvent::Dropped {
+                        from: msg.from(),
+                        reason,
+                        at: self.now,
+                    }
+                } else {
+                    let mut msg = msg;
+                    let node = self.topology.nic(msg.to()).group as usize;
+                    let rate = self.topology.link(link).map_or(0.0, |l| l.bit_error_rate);
+                    if rate > 0.0 && self.corrupt(&mut msg, rate) {
+                        self.stats.corrupted(node, link);
+                    }
+                    self.stats.received(node, link, msg.data().len());
+                    self.stats.delivered_after(self.now - sent_at);
+                    self.deliver(node, msg, sent_at)
+                }
+            }
+            Event::Replay { msg, sent_at } => {
+                let node = self.topology.nic(msg.to()).group as usize;
+                self.deliver(node, msg, sent_at)
+            }
+            Event::Wake { node } => {
+                self.mailboxes[node].wake();
+                SimEvent::Woken { node, at: self.now }
+            }
+            Event::Dequeue { nic } => self.dequeue(nic),
+        };
+        self.emit(event.clone());
+        event
+    }
+
+    /// Whether the receiving node lets `msg` in, by its filter or else its `accept` hook.
+    fn accepts(&self, msg: &IncomingMsg) -> bool {
+        let node = self.topology.nic(msg.to()).group as usize;
+        let nics = Nics::from_slice(self.topology.nics(node)).named(&self.topology.names[node]);
+        match (&self.filters[node], &self.tasks[node]) {
+            (Some(filter), _) => filter(msg, &nics),
+            (None, Some(Task::Idle(inner))) => inner.accept(msg, &nics),
+            // `process` has the node, so only a filter could turn the message away.
+            (None, _) => true,
+        }
+    }
+
+    /// Flip one random bit of each byte with probability `rate`, returning whether any changed.
+    fn corrupt(&mut self, msg: &mut IncomingMsg, rate: f64) -> bool {
+        let mut corrupted = false;
+        let data = msg.data_mut();
+        for byte in data.iter_mut() {
+            if self.rng.next_f64() < rate {
+                *byte ^= 1 << (self.rng.next_u64() % 8);
+                corrupted = true;
+            }
+        }
+        corrupted
+    }
+
+    fn deliver(&mut self, node: usize, msg: IncomingMsg, sent_at: u64) -> SimEvent {
+        let event = SimEvent::Delivered {
+            from: msg.from(),
+            to: msg.to(),
+            bytes: msg.data().len(),
+            at: self.now,
+        };
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.push(Delivery {
+                id: msg.id(),
+                at: self.now,
+                sent_at,
+                from: msg.from(),
+                to: msg.to(),
+                from_node: self
+                    .topology
+                    .nic_index(msg.from())
+                    .map_or(node, |index| self.topology.hardware[index].group as usize),
+                to_node: node,
+                data: msg.data().to_vec(),
+            });
+        }
+        self.mailboxes[node].deliver(msg);
+        self.last_delivery[node] = Some(self.now);
+        event
+    }
+
+    fn schedule(&mut self, at: u64, event: Event) {
+        self.queue.push(Reverse(Scheduled {
+            at,
+            seq: self.seq,
+            event,
+        }));
+        self.seq += 1;
+    }
+
+    /// Activate every node once, so they may begin sending.
+    pub(crate) fn start(&mut self) -> Result<(), SimErr> {
+        self.started = true;
+        self.links_up(0);
+        for node in 0..self.tasks.len() {
+            if let Some(Task::Idle(inner)) = self.tasks[node].take() {
+                self.tasks[node] = Some(Task::Running(self.spawn(node, inner)));
+                self.wakers[node].woken.store(true, Ordering::SeqCst);
+            }
+        }
+        self.poll_nodes()
+    }
+
+    /// Emit `LinkUp` for every link with an id from `first` on.
+    fn links_up(&mut self, first: LinkId) {
+        let links: Vec<LinkId> = self
+            .topology
+            .links
+            .iter()
+            .map(|link| link.id)
+            .filter(|&id| id >= first)
+            .collect();
+        for link in links {
+            self.emit(SimEvent::LinkUp { link, at: self.now });
+        }
+    }
+
+    fn spawn(&self, node: usize, inner: NodeHandle<'a>) -> Process<'a> {
+        let mut mailbox = self.mailboxes[node].handle();
+        let hardware = self.topology.nics(node).to_vec();
+        let name = self.topology.names[node].clone();
+        let mut lent = Lent {
+            node: Some(inner),
+            slot: Arc::clone(&self.reclaimed[node]),
+        };
+        Box::pin(async move {
+            let nics = Nics::from_slice(&hardware).named(&name);
+            let inner = lent.node.as_mut().expect("a running node should be lent");
+            let result = inner.process(&mut mailbox, &nics).await;
+            let inner = lent.node.take().expect("a running node should be lent");
+            (inner, result)
+        })
+    }
+
+    /// Whether an idle node has link changes to hear of.
+    fn has_link_changes(&self) -> bool {
+        self.link_changes
+            .iter()
+            .zip(&self.tasks)
+            .any(|(changes, task)| !changes.is_empty() && matches!(task, Some(Task::Idle(_))))
+    }
+
+    /// Run `Node::on_link_change` for each link change `node` has yet to hear of.
+    fn spawn_link_changes(&mut self, node: usize, inner: NodeHandle<'a>) -> Process<'a> {
+        let changes = mem::take(&mut self.link_changes[node]);
+        let hardware = self.topology.nics(node).to_vec();
+        let name = self.topology.names[node].clone();
+        let mut lent = Lent {
+            node: Some(inner),
+            slot: Arc::clone(&self.reclaimed[node]),
+        };
+        Box::pin(async move {
+            let nics = Nics::from_slice(&hardware).named(&name);
+            let inner = lent.node.as_mut().expect("a running node should be lent");
+            for (nic, up) in changes {
+                inner.on_link_change(nic, up, &nics).await;
+            }
+            let inner = lent.node.take().expect("a running node should be lent");
+            (inner, Ok(Continue::KeepRunning))
+        })
+    }
+
+    /// Poll woken nodes, and activate idle nodes with new mail, until none can make progress.
+    /// Under `SchedulerPolicy::RoundRobin` every node is polled first.
+    fn poll_nodes(&mut self) -> Result<(), SimErr> {
+        // An error cuts the pass short, leaving later nodes to be caught up on by `catch_up`.
+        self.interrupted = true;
+        self.poll_all()?;
+        self.interrupted = false;
+        Ok(())
+    }
+
+    /// Poll the nodes an earlier error kept from being polled, or that have link changes to hear of.
+    fn catch_up(&mut self) -> Result<(), SimErr> {
+        if self.interrupted || self.has_link_changes() {
+            self.poll_nodes()?;
+        }
+        Ok(())
+    }
+
+    fn poll_all(&mut self) -> Result<(), SimErr> {
+        // Round robin polls everyone on the first pass, then only what that pass set off.
+        let mut everyone = self.scheduler == SchedulerPolicy::RoundRobin;
+        loop {
+            let mut progressed = false;
+            for node in 0..self.tasks.len() {
+                let mut process = match self.tasks[node].take() {
+                    Some(Task::Idle(inner)) if !everyone && !self.link_changes[node].is_empty() => {
+                        self.spawn_link_changes(node, inner)
+                    }
+                    Some(Task::Idle(inner)) if self.mailboxes[node].take_unread() || everyone => {
+                        self.spawn(node, inner)
+                    }
+                    Some(Task::Running(process))
+                        if self.wakers[node].woken.swap(false, Ordering::SeqCst) || everyone =>
+                    {
+                        process
+                    }
+                    task => {
+                        self.tasks[node] = task;
+                        continue;
+                    }
+                };
+                progressed = true;
+
+                self.mailboxes[node].set_now(self.now);
+                self.mailboxes[node].set_budget(self.process_budget);
+                let waker = Waker::from(Arc::clone(&self.wakers[node]));
+                let mut cx = Context::from_waker(&waker);
+                let poll = panic::catch_unwind(AssertUnwindSafe(|| process.as_mut().poll(&mut cx)))
+                    .map_err(|payload| SimErr::NodePanicked {
+                        node,
+                        message: panic_message(payload),
+                    })?;
+                self.tasks[node] = match poll {
+                    Poll::Ready((inner, Ok(Continue::KeepRunning))) => Some(Task::Idle(inner)),
+                    Poll::Ready((inner, Ok(Continue::Done))) => Some(Task::Done(inner)),
+                    // A failed node is not run again, but stays in place for `reset`.
+                    Poll::Ready((inner, Err(error))) => {
+                        self.tasks[node] = Some(Task::Done(inner));
+                        return Err(SimErr::NodeFailed { node, error });
+                    }
+                    Poll::Pending => Some(Task::Running(process)),
+                };
+                // Come back for what the budget held back once the rest have had a turn.
+                if self.mailboxes[node].take_exhausted() {
+                    self.wakers[node].woken.store(true, Ordering::SeqCst);
+                }
+                self.transmit(node);
+                for at in self.mailboxes[node].take_timers() {
+                    self.schedule(at, Event::Wake { node });
+                }
+            }
+            if !progressed {
+                return Ok(());
+            }
+            everyone = false;
+        }
+    }
+
+    /// Track a message sent from `from` over half-duplex `link`, which will be the next one scheduled.
+    /// It collides with any message still in flight from the other end.
+    fn take_off(&mut self, link: LinkId, from: NicId) {
+        let mut collided = false;
+        for other in self
+            .in_flight
+            .iter_mut()
+            .filter(|other| other.link == link && other.from != from)
+        {
+            other.collided = true;
+            collided = true;
+        }
+        self.in_flight.push(InFlight {
+            seq: self.seq,
+            link,
+            from,
+            collided,
+        });
+    }
+
+    /// Stop tracking a message as it arrives, returning whether it collided.
+    fn land(&mut self, seq: u64) -> bool {
+        match self.in_flight.iter().position(|msg| msg.seq == seq) {
+            Some(position) => self.in_flight.remove(position).collided,
+            None => false,
+        }
+    }
+
+    /// Schedule the messages a node has sent for delivery to their link partners.
+    fn transmit(&mut self, node: usize) {
+        let outgoing = self.mailboxes[node].take_outgoing();
+        // Replies to a message that just arrived wait for the node to finish handling it.
+        let ready = match self.last_delivery[node] {
+            Some(at) if at == self.now => self.now + self.processing_delays[node],
+            _ => self.now,
+        };
+        // Unless it goes on to take time on the link, a message has left as soon as it is ready.
+        for ticket in outgoing.iter().filter_map(|out| out.ticket) {
+            self.mailboxes[node].departs(ticket, ready);
+        }
+        if self.replaying {
+            return;
+        }
+        for mut out in outgoing {
+            if out.id.is_none() {
+                out.id = Some(self.msg_id());
+            }
+            let Some((link, to)) = self
+                .topology
+                .links
+                .iter()
+                .find_map(|link| Some((link, link.partner(out.from)?)))
+            else {
+                self.stats.dropped(node, None, DropReason::NoLink);
+                self.emit(SimEvent::Dropped {
+                    from: out.from,
+                    reason: DropReason::NoLink,
+                    at: self.now,
+                });
+                continue;
+            };
+            // Nor can frames go backwards over a simplex link.
+            if link.duplex == LinkDuplex::Simplex && out.from != link.nics.0 {
+                self.stats.dropped(node, Some(link.id), DropReason::NoLink);
+                self.emit(SimEvent::Dropped {
+                    from: out.from,
+                    reason: DropReason::NoLink,
+                    at: self.now,
+                });
+                continue;
+            }
+            // Multicasts only go to partners in the group.
+            if out
+                .group
+                .is_some_and(|group| !self.topology.is_member(to, group))
+            {
+                continue;
+            }
+            if out.ttl == 0 {
+                self.stats
+                    .dropped(node, Some(link.id), DropReason::TtlExpired);
+                self.stats.expired(node, link.id);
+                self.emit(SimEvent::Dropped {
+                    from: out.from,
+                    reason: DropReason::TtlExpired,
+                    at: self.now,
+                });
+                continue;
+            }
+            let (id, mut latency, weight) = (
+                link.id,
+                self.topology.directed_latency(link, out.from),
+                link.bandwidth
+                    .and(link.flow_weights.as_ref())
+                    .map(|weights| flow_weight(weights, out.flow)),
+            );
+            if link.jitter > 0 {
+                latency += self.rng.next_u64() % (link.jitter + 1);
+            }
+            if !self.topology.nic(out.from).enabled {
+                self.stats.dropped(node, Some(id), DropReason::NicDisabled);
+                self.emit(SimEvent::Dropped {
+                    from: out.from,
+                    reason: DropReason::NicDisabled,
+                    at: self.now,
+                });
+                continue;
+            }
+            let queued = self.queued.entry(id).or_default();
+            if link.max_queue.is_some_and(|max| *queued >= max) {
+                self.stats.dropped(node, Some(id), DropReason::QueueFull);
+                self.emit(SimEvent::Dropped {
+                    from: out.from,
+                    reason: DropReason::QueueFull,
+                    at: self.now,
+                });
+                continue;
+            }
+            *queued += 1;
+            self.mailboxes[node].launched(out.from, out.data.len());
+            let frame = PendingFrame {
+                node,
+                out,
+                to,
+                link: id,
+                latency,
+                sent_at: self.now,
+                finish: 0,
+            };
+            match weight {
+                Some(weight) => self.enqueue(frame, weight, ready),
+                None => {
+                    let (event, _) = self.launch(frame, ready);
+                    self.emit(event);
+                }
+            }
+        }
+    }
+
+    /// Give out the next `MsgId`.
+    fn msg_id(&mut self) -> MsgId {
+        let id = MsgId(self.next_msg_id);
+        self.next_msg_id += 1;
+        id
+    }
+
+    /// Queue `frame` on its nic's weighted fair queue, to be sent from `ready` on once its turn comes.
+    fn enqueue(&mut self, mut frame: PendingFrame, weight: u32, ready: u64) {
+        let nic = frame.out.from;
+        // It only leaves once dequeued.
+        if let Some(ticket) = frame.out.ticket {
+            self.mailboxes[frame.node].withdraw_departure(ticket);
+        }
+        let queue = self.fair_queues.entry(nic).or_default();
+        let flow = frame.out.flow;
+        let start = queue
+            .last_finish
+            .get(&flow)
+            .map_or(queue.virtual_time, |&last| last.max(queue.virtual_time));
+        // Scaled up so light frames of heavy flows still advance the tag.
+        frame.finish = start + frame.out.data.len() as u64 * 1_000_000 / weight as u64;
+        queue.last_finish.insert(flow, frame.finish);
+        queue.frames.push(frame);
+        if !mem::replace(&mut queue.serving, true) {
+            self.schedule(ready, Event::Dequeue { nic });
+        }
+    }
+
+    /// Send the queued frame from `nic` with the lowest finish tag, and come back once it has left.
+    ///
+    /// # Panics!
+    /// If nothing is queued on the nic.
+    fn dequeue(&mut self, nic: NicId) -> SimEvent {
+        let queue = self
+            .fair_queues
+            .get_mut(&nic)
+            .expect("a dequeued nic should have a queue");
+        let next = queue
+            .frames
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, frame)| frame.finish)
+            .map(|(i, _)| i)
+            .expect("a dequeued nic should have frames queued");
+        let frame = queue.frames.remove(next);
+        queue.virtual_time = frame.finish;
+        let (event, sent) = self.launch(frame, self.now);
+        let queue = self
+            .fair_queues
+            .get_mut(&nic)
+            .expect("a dequeued nic should have a queue");
+        if queue.frames.is_empty() {
+            queue.serving = false;
+        } else {
+            self.schedule(sent, Event::Dequeue { nic });
+        }
+        event
+    }
+
+    /// Put `frame` on its link once it is `ready`, scheduling its delivery.
+    /// Returns the event for it being sent, and when it finishes leaving the nic.
+    fn launch(&mut self, frame: PendingFrame, ready: u64) -> (SimEvent, u64) {
+        let PendingFrame {
+            node,
+            out,
+            to,
+            link: id,
+            latency,
+            sent_at,
+            ..
+        } = frame;
+        // The link may have been torn down while the frame waited on a weighted fair queue.
+        let (duplex, bandwidth, duplicate, forwarding) =
+            self.topology.link(id).map_or(Default::default(), |link| {
+                (
+                    link.duplex,
+                    link.bandwidth,
+                    link.duplicate_probability,
+                    link.forwarding,
+                )
+            });
+        // The frame leaves once the sender has finished with the ones queued before it.
+        let (departs, sent) = match bandwidth {
+            Some(bandwidth) => {
+                let duration = transmission_time(out.data.len(), bandwidth);
+                let sent = self.mailboxes[node].occupy(out.from, ready, duration);
+                if let Some(ticket) = out.ticket {
+                    self.mailboxes[node].departs(ticket, sent);
+                }
+                let departs = match forwarding {
+                    LinkForwarding::StoreAndForward => sent,
+                    LinkForwarding::CutThrough => {
+                        let header = out.data.len().min(ETHERNET_HEADER_LEN);
+                        sent - duration + transmission_time(header, bandwidth)
+                    }
+                };
+                (departs, sent)
+            }
+            None => (ready, ready),
+        };
+        self.stats.sent(node, id, out.data.len());
+        let event = SimEvent::Sent {
+            from: out.from,
+            to,
+            bytes: out.data.len(),
+            at: self.now,
+        };
+        let msg = |data| {
+            IncomingMsg::new(out.from, to, data)
+                .with_priority(out.priority)
+                .with_checksum(out.checksum)
+                .with_ttl(out.ttl)
+                .with_id(out.id.unwrap_or_default())
+        };
+        let arrives = departs + latency;
+        let bytes = out.data.len();
+        let copy = (duplicate > 0.0 && self.rng.next_f64() < duplicate).then(|| out.data.clone());
+        if duplex == LinkDuplex::Half {
+            self.take_off(id, out.from);
+        }
+        let delivery = Event::Deliver {
+            link: id,
+            msg: msg(out.data),
+            sent_at,
+        };
+        self.schedule(arrives, delivery);
+        if let Some(data) = copy {
+            self.stats.duplicated(node, id);
+            self.mailboxes[node].launched(out.from, bytes);
+            *self.queued.entry(id).or_default() += 1;
+            let delivery = Event::Deliver {
+                link: id,
+                msg: msg(data),
+                sent_at,
+            };
+            self.schedule(arrives + 1, delivery);
+        }
+        (event, sent)
+    }
+}
+
+/// The weight `weights` give `flow`, at least 1.
+fn flow_weight(weights: &[(u32, u32)], flow: u32) -> u32 {
+    weights
+        .iter()
+        .find(|(listed, _)| *listed == flow)
+        .map_or(1, |(_, weight)| *weight)
+        .max(1)
+}
+
+/// The message a panic was raised with, if it was a string.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(
+            || "non-string panic payload".to_string(),
+            |message| message.to_string(),
+        ),
+    }
+}
+
+/// The representative of `node`'s set in the union-find forest `parent`, halving the path on the way.
+fn union_find_root(parent: &mut [usize], mut node: usize) -> usize {
+    while parent[node] != node {
+        parent[node] = parent[parent[node]];
+        node = parent[node];
+    }
+    node
+}
+
+/// Nanoseconds to put `bytes` on a link sending `bandwidth` bits per second, rounded up.
+fn transmission_time(bytes: usize, bandwidth: u64) -> u64 {
+    let bits = bytes as u128 * 8 * 1_000_000_000;
+    bits.div_ceil(bandwidth.max(1) as u128)
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
+
+/// How far `run_sim` lets virtual time advance: one minute.
+pub const DEFAULT_MAX_TIME: u64 = 60_000_000_000;
+
+/// How many events a `Sim` lets come due at one instant before failing with `SimErr::Stalled`.
+pub const DEFAULT_INSTANT_LIMIT: usize = 1_000_000;
+
+/// Run `nodes` to completion, or until `DEFAULT_MAX_TIME`, returning the message counters collected.
+pub fn run_sim(nodes: &mut [&mut dyn Node]) -> Result<Stats, SimErr> {
+    run_sim_until(nodes, DEFAULT_MAX_TIME)
+}
+
+/// Like `run_sim`, for nodes built up programmatically, such as with `nodes_vec!`.
+pub fn run_sim_boxed(nodes: &mut [Box<dyn Node>]) -> Result<Stats, SimErr> {
+    let mut nodes: Vec<&mut dyn Node> = nodes
+        .iter_mut()
+        .map(|node| &mut **node as &mut dyn Node)
+        .collect();
+    run_sim(&mut nodes)
+}
+
+/// Run `nodes` to completion, or until virtual time would pass `max_time`.
+pub fn run_sim_until(nodes: &mut [&mut dyn Node], max_time: u64) -> Result<Stats, SimErr> {
+    let mut sim = Sim::build(nodes)?;
+    sim.run_until(max_time)?;
+    Ok(sim.stats)
+}
+
+/// Like `run_sim`, pacing events so each virtual nanosecond takes `scale` wall-clock nanoseconds.
+pub fn run_sim_realtime(nodes: &mut [&mut dyn Node], scale: f64) -> Result<Stats, SimErr> {
+    let mut sim = Sim::build(nodes)?;
+    sim.run_realtime(DEFAULT_MAX_TIME, scale)?;
+    Ok(sim.stats)
+}
+
+/// Like `run_sim`, calling `hook` with every `SimEvent` as it happens.
+pub fn run_sim_with_hook<'a>(
+    nodes: &'a mut [&mut dyn Node],
+    hook: impl FnMut(&SimEvent) + 'a,
+) -> Result<Stats, SimErr> {
+    let mut sim = Sim::build(nodes)?;
+    sim.on_event(hook);
+    sim.run_until(DEFAULT_MAX_TIME)?;
+    Ok(sim.stats)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use smoltcp::wire::EthernetAddress;
+    use crate::nics::DEFAULT_MTU;
 
     fn nic_with_group(group: u64) -> Nic {
         Nic {
@@ -124,10 +2224,39 @@ mod tests {
             group,
             mac: EthernetAddress([0, 0, 0, 0, 0, 0]),
             latency: None,
+            mtu: DEFAULT_MTU,
+            enabled: true,
+            label: None,
+            cost: 1,
+            ip: None,
             link_id: None,
+            partner: None,
+            partner_mac: None,
+            partner_node: None,
+            receive_only: false,
+            metadata: Default::default(),
         }
     }
 
+    #[test]
+    fn fill_links_after_reorder() {
+        let mut hardware: Vec<Nic> = (0..3).map(|id| nic_with_group(id / 2)).collect();
+        for (id, nic) in hardware.iter_mut().enumerate() {
+            nic.id = id as NicId;
+        }
+        // The first node's nics are stored out of id order.
+        hardware.swap(0, 1);
+        let mut topology = Topology::new(hardware, vec![String::new(); 2], 1);
+        topology.link_nics(0, 2);
+        topology.fill_links();
+
+        assert_eq!(topology.nic(0).link_id, Some(0));
+        assert_eq!(topology.nic(1).link_id, None);
+        assert_eq!(topology.nic(2).link_id, Some(0));
+        assert_eq!(topology.all_nics()[1].id, 0);
+        assert_eq!(topology.all_nics()[1].link_id, Some(0));
+    }
+
     #[test]
     fn slice_bounds_check() {
         // CHECK LIST WITH ONE NIC PER NODE
@@ -0,0 +1,130 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Default)]
+struct BarrierState {
+    parties: usize,
+    /// Nodes waiting in the current round.
+    arrived: usize,
+    /// Counts rounds, so a waiter can tell its round was released.
+    round: u64,
+    waiters: Vec<Waker>,
+}
+
+/// Holds nodes back until `parties` of them are waiting on it, then lets them all go at the same instant.
+///
+/// Create one before the simulation and give a clone to each participating node, which awaits `wait` in
+/// `process`. Nodes released together carry on before virtual time advances, and the barrier can then be
+/// waited on again for the next round.
+#[derive(Clone)]
+pub struct Barrier {
+    state: Arc<Mutex<BarrierState>>,
+}
+
+impl Barrier {
+    /// A barrier for `parties` nodes. With no parties, waiting returns at once.
+    pub fn new(parties: usize) -> Self {
+        let state = BarrierState {
+            parties,
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Wait until every party has arrived in this round.
+    pub fn wait(&self) -> BarrierWait<'_> {
+        BarrierWait {
+            barrier: self,
+            round: None,
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, BarrierState> {
+        self.state
+            .lock()
+            .expect("barrier lock should not be poisoned")
+    }
+}
+
+/// A node's wait at a `Barrier`, from `Barrier::wait`. The node counts as arrived from the first poll
+/// until its round is released, or until the wait is dropped first.
+pub struct BarrierWait<'a> {
+    barrier: &'a Barrier,
+    /// Set on the first poll, once the node has arrived.
+    round: Option<u64>,
+}
+
+impl Future for BarrierWait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut state = this.barrier.state();
+        let round = match this.round {
+            Some(round) => round,
+            None => {
+                state.arrived += 1;
+                if state.arrived >= state.parties {
+                    state.arrived = 0;
+                    state.round += 1;
+                    for waiter in state.waiters.drain(..) {
+                        waiter.wake();
+                    }
+                    return Poll::Ready(());
+                }
+                *this.round.insert(state.round)
+            }
+        };
+        if state.round != round {
+            return Poll::Ready(());
+        }
+        if !state
+            .waiters
+            .iter()
+            .any(|waiter| waiter.will_wake(cx.waker()))
+        {
+            state.waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for BarrierWait<'_> {
+    fn drop(&mut self) {
+        // A wait given up on before its round was released no longer counts toward it.
+        if let Some(round) = self.round {
+            let mut state = self.barrier.state();
+            if state.round == round {
+                state.arrived -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(wait: &mut BarrierWait<'_>) -> Poll<()> {
+        Pin::new(wait).poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn dropped_wait_leaves_the_round() {
+        let barrier = Barrier::new(2);
+        let mut early = barrier.wait();
+        assert!(poll(&mut early).is_pending());
+        drop(early);
+
+        // Without the dropped wait, one party is still short.
+        let mut first = barrier.wait();
+        assert!(poll(&mut first).is_pending());
+        let mut second = barrier.wait();
+        assert!(poll(&mut second).is_ready());
+        assert!(poll(&mut first).is_ready());
+    }
+}
@@ -0,0 +1,51 @@
+use crate::nics::{LinkId, NicId};
+
+/// Something that happened in a simulation, at virtual time `at`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimEvent {
+    /// A message left `from` for its link partner `to`.
+    Sent {
+        from: NicId,
+        to: NicId,
+        bytes: usize,
+        at: u64,
+    },
+    /// A message reached the mailbox of `to`.
+    Delivered {
+        from: NicId,
+        to: NicId,
+        bytes: usize,
+        at: u64,
+    },
+    /// A message sent from `from` was lost.
+    Dropped {
+        from: NicId,
+        reason: DropReason,
+        at: u64,
+    },
+    /// A node's timer fired, such as from `Mailbox::recv_timeout`.
+    Woken { node: usize, at: u64 },
+    /// A link began carrying messages.
+    LinkUp { link: LinkId, at: u64 },
+    /// A link was torn down.
+    LinkDown { link: LinkId, at: u64 },
+}
+
+/// Why a message was not delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// The sending nic had no link partner.
+    NoLink,
+    /// The link was torn down while the message was in flight.
+    LinkDown,
+    /// The link already had as many messages in flight as its `max_queue`.
+    QueueFull,
+    /// The sending or receiving nic was disabled.
+    NicDisabled,
+    /// The message collided with one sent from the other end of a half-duplex link.
+    Collision,
+    /// The receiving node's `Node::filter` or `Node::accept` turned the message away.
+    Filtered,
+    /// A node forwarded the message with no hops left on its TTL.
+    TtlExpired,
+}
@@ -0,0 +1,84 @@
+use serde_json::json;
+
+use crate::message::{IncomingMsg, MsgId};
+use crate::nics::NicId;
+use crate::node::Node;
+
+use super::{sim_setup, Event, Sim, SimErr};
+
+/// A message that reached a mailbox.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delivery {
+    /// The id the message was sent with, which replaying gives it again.
+    pub id: MsgId,
+    pub at: u64,
+    /// When the message was handed to the link.
+    pub sent_at: u64,
+    pub from: NicId,
+    pub to: NicId,
+    /// The nodes owning `from` and `to`. Messages injected from outside the simulation count as from `to_node`.
+    pub from_node: usize,
+    pub to_node: usize,
+    pub data: Vec<u8>,
+}
+
+/// Every delivery of a run, in order. Start one with `Sim::record`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimRecorder {
+    deliveries: Vec<Delivery>,
+}
+
+impl SimRecorder {
+    pub fn deliveries(&self) -> &[Delivery] {
+        &self.deliveries
+    }
+
+    /// The deliveries in Chrome's trace event format, for `chrome://tracing` or Perfetto.
+    ///
+    /// Each node is a track, and each delivery a span on the receiving node's track,
+    /// from when the message was sent until it arrived.
+    pub fn to_chrome_trace(&self) -> String {
+        let events: Vec<_> = self
+            .deliveries
+            .iter()
+            .map(|delivery| {
+                json!({
+                    "name": format!("from node {}", delivery.from_node),
+                    "ph": "X",
+                    // Trace timestamps are in microseconds.
+                    "ts": delivery.sent_at as f64 / 1000.0,
+                    "dur": (delivery.at - delivery.sent_at) as f64 / 1000.0,
+                    "pid": 0,
+                    "tid": delivery.to_node,
+                    "args": {
+                        "from": delivery.from,
+                        "to": delivery.to,
+                        "bytes": delivery.data.len(),
+                    },
+                })
+            })
+            .collect();
+        json!({ "traceEvents": events }).to_string()
+    }
+
+    pub(crate) fn push(&mut self, delivery: Delivery) {
+        self.deliveries.push(delivery);
+    }
+}
+
+/// Feed the deliveries in `recorder` to `nodes`, in order and at their recorded times.
+///
+/// `nodes` are set up as usual, and their timers still fire, but whatever they send is discarded:
+/// only the recorded messages arrive. Nodes set up like the recorded ones end in the same state.
+pub fn replay(recorder: &SimRecorder, nodes: &mut [&mut dyn Node]) -> Result<(), SimErr> {
+    let topology = sim_setup(nodes)?;
+    let mut sim = Sim::new(nodes, topology);
+    sim.replaying = true;
+    for delivery in recorder.deliveries() {
+        let msg = IncomingMsg::new(delivery.from, delivery.to, delivery.data.clone())
+            .with_id(delivery.id);
+        let sent_at = delivery.sent_at;
+        sim.schedule(delivery.at, Event::Replay { msg, sent_at });
+    }
+    sim.run()
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use super::event::DropReason;
+use super::stats::Counters;
+
+/// Every reason a message can be dropped for, in the order reports list them.
+const DROP_REASONS: [DropReason; 7] = [
+    DropReason::NoLink,
+    DropReason::LinkDown,
+    DropReason::QueueFull,
+    DropReason::NicDisabled,
+    DropReason::Collision,
+    DropReason::Filtered,
+    DropReason::TtlExpired,
+];
+
+/// A summary of a run, from `Sim::report`. Displays as a table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimReport {
+    /// Virtual time when the report was taken, in nanoseconds.
+    pub now: u64,
+    /// Messages handed to a link, across every node.
+    pub sent: u64,
+    /// Messages delivered to a mailbox, across every node.
+    pub received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// The number of messages dropped for each reason that dropped any.
+    pub drops: Vec<(DropReason, u64)>,
+    /// The name and counters of each node, by index.
+    pub nodes: Vec<(String, Counters)>,
+}
+
+impl SimReport {
+    pub(crate) fn new(
+        now: u64,
+        nodes: Vec<(String, Counters)>,
+        drops: impl Fn(DropReason) -> u64,
+    ) -> Self {
+        let total = |field: fn(&Counters) -> u64| nodes.iter().map(|(_, c)| field(c)).sum();
+        Self {
+            now,
+            sent: total(|c| c.sent),
+            received: total(|c| c.received),
+            bytes_sent: total(|c| c.bytes_sent),
+            bytes_received: total(|c| c.bytes_received),
+            drops: DROP_REASONS
+                .into_iter()
+                .map(|reason| (reason, drops(reason)))
+                .filter(|(_, count)| *count > 0)
+                .collect(),
+            nodes,
+        }
+    }
+
+    /// Messages dropped for any reason.
+    pub fn dropped(&self) -> u64 {
+        self.drops.iter().map(|(_, count)| count).sum()
+    }
+}
+
+impl fmt::Display for SimReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "time:     {} ns", self.now)?;
+        writeln!(
+            f,
+            "messages: {} sent, {} received, {} dropped",
+            self.sent,
+            self.received,
+            self.dropped()
+        )?;
+        writeln!(
+            f,
+            "bytes:    {} sent, {} received",
+            self.bytes_sent, self.bytes_received
+        )?;
+        for (reason, count) in &self.drops {
+            writeln!(f, "  {:<12} {count}", format!("{reason:?}"))?;
+        }
+        writeln!(
+            f,
+            "{:>4} {:>8} {:>8} {:>12} {:>12} {:>8}  name",
+            "node", "sent", "received", "bytes sent", "bytes recv", "dropped"
+        )?;
+        for (node, (name, c)) in self.nodes.iter().enumerate() {
+            writeln!(
+                f,
+                "{node:>4} {:>8} {:>8} {:>12} {:>12} {:>8}  {name}",
+                c.sent, c.received, c.bytes_sent, c.bytes_received, c.dropped
+            )?;
+        }
+        Ok(())
+    }
+}
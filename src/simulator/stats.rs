@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::event::DropReason;
+use crate::nics::LinkId;
+
+/// Message counters for a node or a link.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Messages handed to the link.
+    pub sent: u64,
+    /// Messages delivered to a mailbox.
+    pub received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Messages lost before delivery, such as when their link was torn down in flight.
+    /// Counted against the sending node.
+    pub dropped: u64,
+    /// Of `dropped`, the messages lost to collisions on a half-duplex link.
+    pub collisions: u64,
+    /// Of `dropped`, the messages the receiving node's `Node::filter` or `Node::accept` turned away.
+    pub filtered: u64,
+    /// Of `dropped`, the messages forwarded with their TTL run out. Counted against the forwarding node.
+    pub expired: u64,
+    /// Of `received`, the messages that had bits flipped on the way.
+    pub corrupted: u64,
+    /// Messages the link delivered a second copy of. Counted against the sending node.
+    pub duplicated: u64,
+}
+
+/// How long delivered messages took to arrive after being sent, in nanoseconds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// How many messages took each latency.
+    counts: BTreeMap<u64, u64>,
+    total: u64,
+}
+
+impl LatencyHistogram {
+    /// The number of latencies recorded.
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    /// The smallest latency that at least `percent` of messages arrived within, or `None` if none were recorded.
+    ///
+    /// # Panics!
+    /// If `percent` is not within 0 to 100.
+    pub fn percentile(&self, percent: f64) -> Option<u64> {
+        assert!(
+            (0.0..=100.0).contains(&percent),
+            "percentile should be within 0 to 100"
+        );
+        let rank = ((percent / 100.0 * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.counts.iter().find_map(|(&latency, &count)| {
+            seen += count;
+            (seen >= rank).then_some(latency)
+        })
+    }
+
+    pub fn p50(&self) -> Option<u64> {
+        self.percentile(50.0)
+    }
+
+    pub fn p99(&self) -> Option<u64> {
+        self.percentile(99.0)
+    }
+
+    /// The longest latency recorded.
+    pub fn max(&self) -> Option<u64> {
+        self.counts.keys().next_back().copied()
+    }
+
+    /// Each latency recorded, in increasing order, with how many messages took it.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.counts
+            .iter()
+            .map(|(&latency, &count)| (latency, count))
+    }
+
+    fn record(&mut self, latency: u64) {
+        *self.counts.entry(latency).or_default() += 1;
+        self.total += 1;
+    }
+}
+
+/// Counters collected while a simulation runs, keyed by node index and link id.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    nodes: HashMap<usize, Counters>,
+    links: HashMap<LinkId, Counters>,
+    /// Messages dropped, by why.
+    drops: HashMap<DropReason, u64>,
+    latencies: LatencyHistogram,
+}
+
+impl Stats {
+    /// The counters of node `node`, all zero if it never sent or received.
+    pub fn node(&self, node: usize) -> Counters {
+        self.nodes.get(&node).copied().unwrap_or_default()
+    }
+
+    /// The counters of link `link`, all zero if it never carried a message.
+    pub fn link(&self, link: LinkId) -> Counters {
+        self.links.get(&link).copied().unwrap_or_default()
+    }
+
+    /// The number of messages dropped for `reason`.
+    pub fn drops(&self, reason: DropReason) -> u64 {
+        self.drops.get(&reason).copied().unwrap_or_default()
+    }
+
+    /// The latencies of every message delivered over a link, from when it was sent to when it arrived.
+    pub fn latencies(&self) -> &LatencyHistogram {
+        &self.latencies
+    }
+
+    pub(crate) fn sent(&mut self, node: usize, link: LinkId, bytes: usize) {
+        for counters in [
+            self.nodes.entry(node).or_default(),
+            self.links.entry(link).or_default(),
+        ] {
+            counters.sent += 1;
+            counters.bytes_sent += bytes as u64;
+        }
+    }
+
+    pub(crate) fn received(&mut self, node: usize, link: LinkId, bytes: usize) {
+        for counters in [
+            self.nodes.entry(node).or_default(),
+            self.links.entry(link).or_default(),
+        ] {
+            counters.received += 1;
+            counters.bytes_received += bytes as u64;
+        }
+    }
+
+    pub(crate) fn delivered_after(&mut self, latency: u64) {
+        self.latencies.record(latency);
+    }
+
+    pub(crate) fn collided(&mut self, node: usize, link: LinkId) {
+        self.nodes.entry(node).or_default().collisions += 1;
+        self.links.entry(link).or_default().collisions += 1;
+    }
+
+    pub(crate) fn filtered(&mut self, node: usize, link: LinkId) {
+        self.nodes.entry(node).or_default().filtered += 1;
+        self.links.entry(link).or_default().filtered += 1;
+    }
+
+    pub(crate) fn expired(&mut self, node: usize, link: LinkId) {
+        self.nodes.entry(node).or_default().expired += 1;
+        self.links.entry(link).or_default().expired += 1;
+    }
+
+    pub(crate) fn corrupted(&mut self, node: usize, link: LinkId) {
+        self.nodes.entry(node).or_default().corrupted += 1;
+        self.links.entry(link).or_default().corrupted += 1;
+    }
+
+    pub(crate) fn duplicated(&mut self, node: usize, link: LinkId) {
+        self.nodes.entry(node).or_default().duplicated += 1;
+        self.links.entry(link).or_default().duplicated += 1;
+    }
+
+    pub(crate) fn dropped(&mut self, node: usize, link: Option<LinkId>, reason: DropReason) {
+        self.nodes.entry(node).or_default().dropped += 1;
+        *self.drops.entry(reason).or_default() += 1;
+        if let Some(link) = link {
+            self.links.entry(link).or_default().dropped += 1;
+        }
+    }
+}
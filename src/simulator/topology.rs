@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use smoltcp::wire::EthernetAddress;
+
+use super::{sim_setup, Sim, SimErr, Topology};
+use crate::message::Mailbox;
+use crate::nics::{NicAllocator, Nics, NicsMut};
+use crate::node::{Continue, Node, NodeError};
+
+/// Set up `nodes` as usual, then link each pair of nodes in `edges` through their first free nics.
+fn generate(
+    nodes: &mut [&mut dyn Node],
+    edges: impl IntoIterator<Item = (usize, usize)>,
+) -> Result<Topology, SimErr> {
+    let mut topology = sim_setup(nodes)?;
+    for (a, b) in edges {
+        let nic_a = topology.free_nic(a).ok_or(SimErr::NoFreeNic { node: a })?;
+        let nic_b = topology.free_nic(b).ok_or(SimErr::NoFreeNic { node: b })?;
+        topology.link_nics(nic_a, nic_b);
+    }
+    topology.fill_links();
+    Ok(topology)
+}
+
+/// Link every node to the next, and the last back to the first.
+/// Fewer than three nodes form a chain.
+pub fn ring(nodes: &mut [&mut dyn Node]) -> Result<Topology, SimErr> {
+    let n = nodes.len();
+    let edges: Vec<_> = match n {
+        0 | 1 => Vec::new(),
+        2 => vec![(0, 1)],
+        _ => (0..n).map(|i| (i, (i + 1) % n)).collect(),
+    };
+    generate(nodes, edges)
+}
+
+/// Link the first node to every other node.
+pub fn star(nodes: &mut [&mut dyn Node]) -> Result<Topology, SimErr> {
+    let edges: Vec<_> = (1..nodes.len()).map(|i| (0, i)).collect();
+    generate(nodes, edges)
+}
+
+/// Lay the nodes out in row-major order and link each to its right and lower neighbors.
+///
+/// # Panics!
+/// If the number of nodes is not `rows * cols`.
+pub fn grid(nodes: &mut [&mut dyn Node], rows: usize, cols: usize) -> Result<Topology, SimErr> {
+    assert_eq!(nodes.len(), rows * cols, "a grid needs `rows * cols` nodes");
+    let mut edges = Vec::new();
+    for row in 0..rows {
+        for col in 0..cols {
+            let node = row * cols + col;
+            if col + 1 < cols {
+                edges.push((node, node + 1));
+            }
+            if row + 1 < rows {
+                edges.push((node, node + cols));
+            }
+        }
+    }
+    generate(nodes, edges)
+}
+
+/// Link every node to every other node.
+pub fn full_mesh(nodes: &mut [&mut dyn Node]) -> Result<Topology, SimErr> {
+    let n = nodes.len();
+    let edges: Vec<_> = (0..n)
+        .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+        .collect();
+    generate(nodes, edges)
+}
+
+/// A node declared through `TopologyBuilder`, with hardware but no behavior of its own.
+struct BuiltNode {
+    name: String,
+    macs: Vec<EthernetAddress>,
+}
+
+#[async_trait]
+impl Node for BuiltNode {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for mac in &self.macs {
+            nics.nic(*mac, None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Declare nodes and the links between them by name, without implementing `Node`.
+/// e.g. `TopologyBuilder::new().node("A").nic(ETH0).node("B").nic(ETH1).connect("A", "B")`
+#[derive(Default)]
+pub struct TopologyBuilder {
+    nodes: Vec<BuiltNode>,
+    connections: Vec<(String, String)>,
+}
+
+impl TopologyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a node. Following calls to `nic` add hardware to it.
+    pub fn node(mut self, name: &str) -> Self {
+        self.nodes.push(BuiltNode {
+            name: name.to_string(),
+            macs: Vec::new(),
+        });
+        self
+    }
+
+    /// Add a nic to the most recently declared node.
+    ///
+    /// # Panics!
+    /// If no node has been declared yet.
+    pub fn nic(mut self, mac: EthernetAddress) -> Self {
+        self.nodes
+            .last_mut()
+            .expect("a node should be declared before its nics")
+            .macs
+            .push(mac);
+        self
+    }
+
+    /// Link two nodes through their first free nics.
+    /// The nodes may be declared before or after the connection.
+    pub fn connect(mut self, a: &str, b: &str) -> Self {
+        self.connections.push((a.to_string(), b.to_string()));
+        self
+    }
+
+    /// Set up the declared nodes and connections, ready to run.
+    pub fn build(self) -> Result<Sim<'static>, SimErr> {
+        let find = |name: &String| {
+            self.nodes
+                .iter()
+                .position(|node| node.name == *name)
+                .ok_or_else(|| SimErr::UnknownNode { name: name.clone() })
+        };
+        let edges = self
+            .connections
+            .iter()
+            .map(|(a, b)| Ok((find(a)?, find(b)?)))
+            .collect::<Result<Vec<_>, SimErr>>()?;
+
+        let mut nodes: Vec<Box<dyn Node>> = self
+            .nodes
+            .into_iter()
+            .map(|node| Box::new(node) as Box<dyn Node>)
+            .collect();
+        let topology = {
+            // The cast shortens the trait object's lifetime, so `nodes` is free to move afterwards.
+            let mut borrowed: Vec<&mut dyn Node> = nodes
+                .iter_mut()
+                .map(|node| node.as_mut() as &mut dyn Node)
+                .collect();
+            generate(&mut borrowed, edges)?
+        };
+        Ok(Sim::from_owned(nodes, topology))
+    }
+}
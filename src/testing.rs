@@ -0,0 +1,61 @@
+//! Helpers for testing nodes against each other.
+
+use crate::node::Node;
+use crate::simulator::recorder::Delivery;
+use crate::simulator::{Sim, SimErr};
+
+/// Runs a simulation with every delivery recorded, to assert on what reached which node.
+pub struct TestHarness<'a> {
+    sim: Sim<'a>,
+}
+
+impl<'a> TestHarness<'a> {
+    /// Set up the hardware and links of `nodes`, recording from the start.
+    pub fn new(nodes: &'a mut [&mut dyn Node]) -> Result<Self, SimErr> {
+        let mut sim = Sim::build(nodes)?;
+        sim.record();
+        Ok(Self { sim })
+    }
+
+    /// The simulation being tested, to adjust the topology or step through it by hand.
+    pub fn sim(&mut self) -> &mut Sim<'a> {
+        &mut self.sim
+    }
+
+    /// Run until no messages are left to deliver.
+    pub fn run(&mut self) -> Result<(), SimErr> {
+        self.sim.run()
+    }
+
+    /// Every delivery so far, in order.
+    pub fn deliveries(&self) -> &[Delivery] {
+        self.sim
+            .recorder()
+            .expect("the harness should always be recording")
+            .deliveries()
+    }
+
+    /// Run the simulation, then return the first delivery of `bytes` from node `from` to node `to`.
+    ///
+    /// # Panics!
+    /// If the simulation fails, or `bytes` never made it from `from` to `to`.
+    pub fn expect_delivery(&mut self, from: usize, to: usize, bytes: &[u8]) -> &Delivery {
+        if let Err(err) = self.run() {
+            panic!("simulation failed before node {from} reached node {to}: {err:?}");
+        }
+        let delivered = self.deliveries();
+        match delivered
+            .iter()
+            .position(|d| d.from_node == from && d.to_node == to && d.data == bytes)
+        {
+            Some(index) => &self.deliveries()[index],
+            None => panic!(
+                "expected {bytes:?} from node {from} to reach node {to}, but delivered were {:?}",
+                delivered
+                    .iter()
+                    .map(|d| (d.from_node, d.to_node, &d.data))
+                    .collect::<Vec<_>>()
+            ),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::ack::{AckError, AckTracker};
+use crate::message::Mailbox;
+use crate::simulator::Sim;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Sends one reliable message to its neighbor when the simulation starts.
+struct ReliableSender {
+    eth: EthernetAddress,
+    neighbor: EthernetAddress,
+    tracker: AckTracker,
+    result: Option<Result<(), AckError>>,
+}
+
+impl ReliableSender {
+    fn new(eth: EthernetAddress, neighbor: EthernetAddress) -> Self {
+        Self {
+            eth,
+            neighbor,
+            tracker: AckTracker::new(Duration::from_nanos(1000)),
+            result: None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for ReliableSender {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, Some(50));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.neighbor).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.result.is_none() {
+            let result = self
+                .tracker
+                .send_reliable(mail, nics, &nics[0], b"important".to_vec())
+                .await;
+            self.result = Some(result);
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Keeps every message it receives through an `AckTracker`.
+struct ReliableReceiver {
+    eth: EthernetAddress,
+    tracker: AckTracker,
+    received: Vec<Vec<u8>>,
+}
+
+impl ReliableReceiver {
+    fn new(eth: EthernetAddress, auto_ack: bool) -> Self {
+        Self {
+            eth,
+            tracker: AckTracker::new(Duration::from_nanos(1000)).auto_ack(auto_ack),
+            received: Vec::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for ReliableReceiver {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, Some(50));
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        let msg = self.tracker.recv(mail, nics).await?;
+        self.received.push(msg.into_data());
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn send_reliable_is_acknowledged() {
+    let mut sender = ReliableSender::new(ETH0, ETH1);
+    let mut receiver = ReliableReceiver::new(ETH1, true);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    // The message, then its acknowledgement.
+    assert_eq!(sim.stats().link(0).received, 2);
+    drop(sim);
+
+    assert_eq!(sender.result, Some(Ok(())));
+    assert_eq!(receiver.received, vec![b"important".to_vec()]);
+}
+
+#[test]
+fn send_reliable_times_out() {
+    let mut sender = ReliableSender::new(ETH2, ETH3);
+    let mut receiver = ReliableReceiver::new(ETH3, false);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 1000);
+    drop(sim);
+
+    assert_eq!(sender.result, Some(Err(AckError::Timeout)));
+    assert_eq!(receiver.received.len(), 1);
+}
@@ -0,0 +1,34 @@
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::node::Node;
+use crate::nodes;
+use crate::simulator::Sim;
+
+#[test]
+fn add_node_to_running_sim() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(1000);
+    let mut receiver = Receiver::new(ETH1).nic(ETH2);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+
+    assert!(sim.step().expect("First message is delivered").is_some());
+    assert_eq!(sim.now(), 1000);
+
+    let node = sim
+        .add_node(Box::new(Sender::new(ETH3, ETH2).latency(50)))
+        .expect("Node is added");
+    assert_eq!(node, 2);
+
+    let nics = sim.topology.nics(node);
+    assert_eq!(nics.len(), 1);
+    assert_eq!(nics[0].id, 3);
+    assert_eq!(nics[0].group, 2);
+    assert_eq!(sim.topology.links.len(), 2);
+    assert_eq!(sim.topology.links[1], (3u64, 2u64));
+    assert!(sim.topology.nics(1)[1].link_id.is_some());
+
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 1050);
+    drop(sim);
+    assert_eq!(receiver.received, 2);
+}
@@ -0,0 +1,62 @@
+use smoltcp::wire::{EthernetAddress, Ipv4Address};
+
+use super::{ETH0, ETH1};
+use crate::message::Mailbox;
+use crate::node::arp::{ArpNode, ArpTable};
+use crate::simulator::run_sim;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+const IP_A: Ipv4Address = Ipv4Address::new(10, 0, 0, 1);
+const IP_B: Ipv4Address = Ipv4Address::new(10, 0, 0, 2);
+
+/// Resolves `target` when the simulation starts.
+struct Resolver {
+    neighbor: EthernetAddress,
+    target: Ipv4Address,
+    table: ArpTable,
+    resolved: Option<EthernetAddress>,
+}
+
+#[async_trait::async_trait]
+impl Node for Resolver {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, Some(10));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.neighbor).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.resolved.is_none() {
+            self.resolved = Some(self.table.resolve(mail, nics, self.target).await?);
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn resolve_neighbor_mac() {
+    let mut resolver = Resolver {
+        neighbor: ETH1,
+        target: IP_B,
+        table: ArpTable::new(IP_A),
+        resolved: None,
+    };
+    let mut host = ArpNode::new(ETH1, IP_B);
+    let stats = run_sim(nodes![resolver, host]).expect("Sim runs to completion");
+
+    // One request, one reply.
+    assert_eq!(stats.link(0).received, 2);
+    assert_eq!(resolver.resolved, Some(ETH1));
+    assert_eq!(resolver.table.lookup(&IP_B), Some(ETH1));
+    assert_eq!(host.table().lookup(&IP_A), Some(ETH0));
+}
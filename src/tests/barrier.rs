@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::{ETH0, ETH1, ETH2};
+use crate::message::Mailbox;
+use crate::simulator::{barrier::Barrier, run_sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Works for `delay`, then waits at the barrier.
+struct Phase {
+    eth: EthernetAddress,
+    delay: u64,
+    barrier: Barrier,
+    arrived_at: Option<u64>,
+    left_at: Option<u64>,
+}
+
+impl Phase {
+    fn new(eth: EthernetAddress, delay: u64, barrier: &Barrier) -> Self {
+        Self {
+            eth,
+            delay,
+            barrier: barrier.clone(),
+            arrived_at: None,
+            left_at: None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Phase {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        if self.delay > 0 {
+            mail.recv_timeout(Duration::from_nanos(self.delay)).await;
+        }
+        self.arrived_at = Some(mail.now());
+        self.barrier.wait().await;
+        self.left_at = Some(mail.now());
+        Ok(Continue::Done)
+    }
+}
+
+#[test]
+fn barrier_holds_nodes_until_all_arrive() {
+    let barrier = Barrier::new(3);
+    let mut a = Phase::new(ETH0, 0, &barrier);
+    let mut b = Phase::new(ETH1, 200, &barrier);
+    let mut c = Phase::new(ETH2, 100, &barrier);
+    run_sim(nodes![a, b, c]).expect("Sim runs to completion");
+
+    let arrived = [a.arrived_at, b.arrived_at, c.arrived_at];
+    assert_eq!(arrived, [Some(0), Some(200), Some(100)]);
+    // Released together, as soon as the last one arrived.
+    let left = [a.left_at, b.left_at, c.left_at];
+    assert_eq!(left, [Some(200); 3]);
+}
+
+#[test]
+fn barrier_short_of_parties_never_releases() {
+    let barrier = Barrier::new(3);
+    let mut a = Phase::new(ETH0, 0, &barrier);
+    let mut b = Phase::new(ETH1, 50, &barrier);
+    run_sim(nodes![a, b]).expect("Sim runs to completion");
+
+    assert_eq!((a.arrived_at, b.arrived_at), (Some(0), Some(50)));
+    assert_eq!((a.left_at, b.left_at), (None, None));
+}
@@ -0,0 +1,120 @@
+use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::icmp;
+use smoltcp::time::Instant;
+use smoltcp::wire::{EthernetAddress, Icmpv4Packet, Icmpv4Repr, IpAddress, IpCidr, Ipv4Address};
+
+use super::{ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::nics::device::NicDevice;
+use crate::simulator::run_sim;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+const IDENT: u16 = 0x22b;
+const PAYLOAD: &[u8] = b"ping";
+
+/// Runs a smoltcp interface on its only nic, pinging `target` once if it is set.
+struct Host {
+    eth: EthernetAddress,
+    neighbor: Option<EthernetAddress>,
+    addr: Ipv4Address,
+    target: Option<Ipv4Address>,
+    iface: Option<(Interface, SocketSet<'static>, SocketHandle)>,
+    replies: usize,
+}
+
+impl Host {
+    fn new(eth: EthernetAddress, addr: Ipv4Address) -> Self {
+        Self {
+            eth,
+            neighbor: None,
+            addr,
+            target: None,
+            iface: None,
+            replies: 0,
+        }
+    }
+
+    fn ping(mut self, neighbor: EthernetAddress, target: Ipv4Address) -> Self {
+        self.neighbor = Some(neighbor);
+        self.target = Some(target);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Host {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(neighbor) = self.neighbor {
+            nics.link(nics[0].id, &neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        let mut device = NicDevice::new(mail, &nics[0]);
+        let (iface, sockets, handle) = self.iface.get_or_insert_with(|| {
+            let config = Config::new(self.eth.into());
+            let mut iface = Interface::new(config, &mut device, Instant::ZERO);
+            iface.update_ip_addrs(|addrs| {
+                addrs.push(IpCidr::new(self.addr.into(), 24)).unwrap();
+            });
+            let mut socket = icmp::Socket::new(
+                icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 256]),
+                icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![0; 256]),
+            );
+            socket.bind(icmp::Endpoint::Ident(IDENT)).unwrap();
+            let mut sockets = SocketSet::new(Vec::new());
+            let handle = sockets.add(socket);
+            (iface, sockets, handle)
+        });
+
+        let socket = sockets.get_mut::<icmp::Socket>(*handle);
+        if let Some(target) = self.target.take() {
+            let request = Icmpv4Repr::EchoRequest {
+                ident: IDENT,
+                seq_no: 0,
+                data: PAYLOAD,
+            };
+            let buf = socket
+                .send(request.buffer_len(), IpAddress::Ipv4(target))
+                .unwrap();
+            request.emit(
+                &mut Icmpv4Packet::new_unchecked(buf),
+                &ChecksumCapabilities::default(),
+            );
+        }
+        iface.poll(Instant::ZERO, &mut device, sockets);
+
+        let socket = sockets.get_mut::<icmp::Socket>(*handle);
+        while let Ok((reply, _)) = socket.recv() {
+            let packet = Icmpv4Packet::new_checked(reply).unwrap();
+            let repr = Icmpv4Repr::parse(&packet, &ChecksumCapabilities::default()).unwrap();
+            if let Icmpv4Repr::EchoReply { data, .. } = repr {
+                assert_eq!(data, PAYLOAD);
+                self.replies += 1;
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn ping_across_link() {
+    let mut client =
+        Host::new(ETH2, Ipv4Address::new(10, 0, 0, 1)).ping(ETH3, Ipv4Address::new(10, 0, 0, 2));
+    let mut server = Host::new(ETH3, Ipv4Address::new(10, 0, 0, 2));
+    run_sim(nodes![client, server]).expect("Sim runs to completion");
+    assert_eq!(client.replies, 1);
+}
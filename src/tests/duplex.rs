@@ -0,0 +1,186 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::simulator::{event::SimEvent, sim_setup, LinkDuplex, Sim};
+use crate::{
+    nics::{NicAllocator, NicError, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Sends a message when the simulation starts, over a link its neighbor made.
+struct Talker {
+    eth: EthernetAddress,
+    received: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for Talker {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if mail.try_recv().is_some() {
+            self.received += 1;
+        } else {
+            mail.send(&nics[0], b"hi".to_vec())?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn half_duplex_collision_drops_both() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10);
+    let mut talker = Talker {
+        eth: ETH1,
+        received: 0,
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, talker];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_duplex(link, LinkDuplex::Half));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+
+    let link = sim.stats().link(0);
+    assert_eq!(link.sent, 2);
+    assert_eq!(link.received, 0);
+    assert_eq!(link.dropped, 2);
+    assert_eq!(link.collisions, 2);
+    drop(sim);
+    assert_eq!(talker.received, 0);
+}
+
+#[test]
+fn half_duplex_one_way_is_delivered() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(10).count(2);
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    topology.set_duplex(link, LinkDuplex::Half);
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+
+    let link = sim.stats().link(0);
+    assert_eq!(link.received, 2);
+    assert_eq!(link.collisions, 0);
+}
+
+#[test]
+fn full_duplex_carries_both_ways() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10);
+    let mut talker = Talker {
+        eth: ETH1,
+        received: 0,
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, talker];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().link(0).received, 2);
+    drop(sim);
+    assert_eq!(talker.received, 1);
+}
+
+#[test]
+fn asymmetric_latency_by_direction() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(10);
+    let mut talker = Talker {
+        eth: ETH3,
+        received: 0,
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, talker];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_asymmetric_latency(link, Some((100, 400))));
+    assert!(!topology.set_asymmetric_latency(7, Some((100, 400))));
+    let mut deliveries = Vec::new();
+    let mut sim = Sim::new(nodes, topology);
+    sim.on_event(|event| {
+        if let SimEvent::Delivered { from, at, .. } = event {
+            deliveries.push((*from, *at));
+        }
+    });
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    assert_eq!(deliveries, [(0, 100), (1, 400)]);
+    assert_eq!(talker.received, 1);
+}
+
+/// Tries to send a message when the simulation starts, keeping the outcome, and counts what it receives.
+struct Attempt {
+    eth: EthernetAddress,
+    sent: Option<Result<(), NodeError>>,
+    received: usize,
+}
+
+impl Attempt {
+    fn new(eth: EthernetAddress) -> Self {
+        Self {
+            eth,
+            sent: None,
+            received: 0,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Attempt {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.sent.is_none() {
+            self.sent = Some(mail.send(&nics[0], b"hi".to_vec()));
+        }
+        while mail.try_recv().is_some() {
+            self.received += 1;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn simplex_link_only_delivers_forwards() {
+    let mut beacon = Attempt::new(ETH0);
+    let mut sensor = Attempt::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![beacon, sensor];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_simplex(0, 1).expect("Both nics are free");
+    assert!(matches!(
+        topology.link_simplex(1, 0),
+        Err(NicError::AlreadyLinked)
+    ));
+    assert!(matches!(
+        topology.link_simplex(9, 0),
+        Err(NicError::NeighborNotFound)
+    ));
+    // Only the forward direction counts as a path.
+    assert_eq!(topology.shortest_path(0, 1), Some(vec![0, 1]));
+    assert_eq!(topology.shortest_path(1, 0), None);
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().link(link).received, 1);
+    drop(sim);
+
+    assert_eq!(beacon.sent, Some(Ok(())));
+    assert_eq!(sensor.sent, Some(Err(NodeError::LinkDown)));
+    assert_eq!((beacon.received, sensor.received), (0, 1));
+}
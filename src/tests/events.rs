@@ -0,0 +1,183 @@
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::simulator::event::{DropReason, SimEvent};
+use crate::simulator::{run_sim_with_hook, sim_setup, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+#[test]
+fn hook_sees_simple_send() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(100);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut events = Vec::new();
+    run_sim_with_hook(nodes, |event| events.push(event.clone())).expect("Sim runs to completion");
+
+    assert_eq!(
+        events,
+        vec![
+            SimEvent::LinkUp { link: 0, at: 0 },
+            SimEvent::Sent {
+                from: 0,
+                to: 1,
+                bytes: 5,
+                at: 0
+            },
+            SimEvent::Delivered {
+                from: 0,
+                to: 1,
+                bytes: 5,
+                at: 100
+            },
+        ]
+    );
+}
+
+#[test]
+fn hook_sees_link_down_drop() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(100);
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut events = Vec::new();
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.on_event(|event| events.push(event.clone()));
+    sim.start().expect("Nodes start");
+    let link = sim.topology().link_between(0, 1).expect("Nodes are linked");
+    sim.unlink(link).expect("Link exists");
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+
+    assert_eq!(events[2], SimEvent::LinkDown { link: 0, at: 0 });
+    assert_eq!(
+        events[3],
+        SimEvent::Dropped {
+            from: 0,
+            reason: DropReason::LinkDown,
+            at: 100
+        }
+    );
+}
+
+#[test]
+fn step_returns_each_event() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10).count(2);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+
+    let delivered = SimEvent::Delivered {
+        from: 0,
+        to: 1,
+        bytes: 5,
+        at: 10,
+    };
+    for _ in 0..2 {
+        assert_eq!(
+            sim.step().expect("Message is delivered"),
+            Some(delivered.clone())
+        );
+        assert_eq!(sim.now(), 10);
+    }
+    assert_eq!(sim.step().expect("Nothing left to do"), None);
+}
+
+#[test]
+fn paused_sim_keeps_its_queue() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10).count(3);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    // Each frame takes 40ns to send, so they arrive 40ns apart.
+    assert!(topology.set_bandwidth(link, Some(1_000_000_000)));
+    let mut sim = Sim::new(nodes, topology);
+
+    sim.step().expect("Message is delivered");
+    assert_eq!(sim.now(), 50);
+    sim.pause();
+    assert!(sim.is_paused());
+    assert_eq!(sim.step().expect("Paused"), None);
+    sim.run().expect("Paused");
+    assert_eq!(sim.now(), 50);
+    assert_eq!(sim.stats().link(0).received, 1);
+
+    sim.resume();
+    assert!(!sim.is_paused());
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 130);
+    drop(sim);
+    assert_eq!(receiver.received, 3);
+}
+
+#[test]
+fn run_until_first_drop() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(100);
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.start().expect("Nodes start");
+    let link = sim.topology().link_between(0, 1).expect("Nodes are linked");
+    sim.unlink(link).expect("Link exists");
+
+    let event = sim
+        .run_until_event(|event| matches!(event, SimEvent::Dropped { .. }))
+        .expect("Sim runs");
+    assert!(matches!(
+        event,
+        Some(SimEvent::Dropped {
+            reason: DropReason::LinkDown,
+            at: 100,
+            ..
+        })
+    ));
+    assert!(sim.is_paused());
+    assert_eq!(
+        sim.run_until_event(|_| false)
+            .expect("Sim runs to completion"),
+        None
+    );
+}
+
+/// Records how many messages are waiting each time it is activated.
+struct Tally {
+    batches: Vec<usize>,
+}
+
+#[async_trait::async_trait]
+impl Node for Tally {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH2, None);
+        nics.nic(ETH3, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        let batch = mail.drain().len();
+        if batch > 0 {
+            self.batches.push(batch);
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn same_tick_deliveries_are_seen_together() {
+    let mut a = Sender::new(ETH0, ETH2).latency(10);
+    let mut b = Sender::new(ETH1, ETH3).latency(10);
+    let mut tally = Tally {
+        batches: Vec::new(),
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![a, b, tally];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    let events = sim.flush().expect("Messages are delivered");
+    assert_eq!(events.len(), 2);
+    assert_eq!(sim.now(), 10);
+    assert!(sim.flush().expect("Nothing left to do").is_empty());
+    drop(sim);
+    assert_eq!(tally.batches, [2]);
+}
@@ -0,0 +1,86 @@
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use super::nodes::Receiver;
+use super::{ETH0, ETH1};
+use crate::message::Mailbox;
+use crate::node::external::ExternalNode;
+use crate::simulator::{run_sim, sim_setup, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Sends back every message it receives, reversed.
+struct Reverser;
+
+#[async_trait::async_trait]
+impl Node for Reverser {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH0).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            let mut data = msg.into_data();
+            data.reverse();
+            mail.send(&nics[0], data)?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn udp_datagram_loops_through_the_sim() {
+    let host = UdpSocket::bind("127.0.0.1:0").expect("host socket binds");
+    host.set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("host socket takes a timeout");
+    let bridge = UdpSocket::bind("127.0.0.1:0").expect("bridge socket binds");
+    let bridge_addr = bridge.local_addr().expect("bridge socket has an address");
+    let host_addr = host.local_addr().expect("host socket has an address");
+    host.send_to(b"ping", bridge_addr)
+        .expect("host sends to the bridge");
+
+    let mut external = ExternalNode::new(ETH0, bridge, host_addr)
+        .expect("bridge socket goes non-blocking")
+        .idle_polls(10);
+    let mut reverser = Reverser;
+    run_sim(nodes![external, reverser]).expect("Sim runs to completion");
+
+    let mut buf = [0; 16];
+    let (len, from) = host.recv_from(&mut buf).expect("reply arrives");
+    assert_eq!(from, bridge_addr);
+    assert_eq!(&buf[..len], b"gnip");
+}
+
+#[test]
+fn datagram_the_nic_cannot_send_is_dropped() {
+    let host = UdpSocket::bind("127.0.0.1:0").expect("host socket binds");
+    let bridge = UdpSocket::bind("127.0.0.1:0").expect("bridge socket binds");
+    let bridge_addr = bridge.local_addr().expect("bridge socket has an address");
+    let host_addr = host.local_addr().expect("host socket has an address");
+    host.send_to(b"ping", bridge_addr)
+        .expect("host sends to the bridge");
+
+    let mut external = ExternalNode::new(ETH0, bridge, host_addr)
+        .expect("bridge socket goes non-blocking")
+        .idle_polls(10);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![external, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    // The bridge can only receive.
+    topology.link_simplex(1, 0).expect("Nics are free");
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(0).sent, 0);
+    assert_eq!(sim.stats().node(1).received, 0);
+}
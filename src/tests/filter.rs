@@ -0,0 +1,108 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::Sender;
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::{IncomingMsg, Mailbox};
+use crate::simulator::event::{DropReason, SimEvent};
+use crate::simulator::Sim;
+use crate::{
+    nics::{NicAllocator, NicId, Nics, NicsMut},
+    node::{Continue, MessageFilter, Node, NodeError},
+    nodes,
+};
+
+/// Turns away every message sent from the nic `blocked`, and records where the rest came from.
+struct Firewall {
+    eths: [EthernetAddress; 2],
+    blocked: NicId,
+    from: Vec<NicId>,
+}
+
+#[async_trait::async_trait]
+impl Node for Firewall {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for eth in self.eths {
+            nics.nic(eth, None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    fn accept(&self, msg: &IncomingMsg, _: &Nics<'_>) -> bool {
+        msg.from() != self.blocked
+    }
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            self.from.push(msg.from());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn rejected_frames_never_reach_the_mailbox() {
+    let mut blocked = Sender::new(ETH0, ETH2).count(2);
+    let mut allowed = Sender::new(ETH1, ETH3);
+    let mut firewall = Firewall {
+        eths: [ETH2, ETH3],
+        blocked: 0,
+        from: Vec::new(),
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![blocked, allowed, firewall];
+    let mut filtered = 0;
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.on_event(|event| {
+        if let SimEvent::Dropped {
+            reason: DropReason::Filtered,
+            ..
+        } = event
+        {
+            filtered += 1;
+        }
+    });
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(0).dropped, 2);
+    assert_eq!(sim.stats().node(0).filtered, 2);
+    assert_eq!(sim.stats().node(2).received, 1);
+    drop(sim);
+    assert_eq!(filtered, 2);
+    assert_eq!(firewall.from, [1]);
+}
+
+/// Turns away every message, while waiting on its mailbox for one.
+struct Closed {
+    received: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for Closed {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    fn filter(&self) -> Option<MessageFilter> {
+        Some(Box::new(|_: &IncomingMsg, _: &Nics<'_>| false))
+    }
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        mail.recv().await;
+        self.received += 1;
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn filter_applies_while_process_waits_on_the_mailbox() {
+    let mut sender = Sender::new(ETH0, ETH1);
+    let mut closed = Closed { received: 0 };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, closed];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(0).filtered, 1);
+    assert_eq!(sim.stats().node(1).received, 0);
+    drop(sim);
+    assert_eq!(closed.received, 0);
+}
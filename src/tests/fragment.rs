@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::{ETH0, ETH1};
+use crate::message::fragment::FragmentingMailbox;
+use crate::message::Mailbox;
+use crate::simulator::run_sim;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+const MTU: usize = 64;
+
+/// Sends `payload` through a `FragmentingMailbox` when the simulation starts,
+/// or, with no neighbor, keeps the message it reassembles.
+struct Host {
+    eth: EthernetAddress,
+    neighbor: Option<EthernetAddress>,
+    payload: Vec<u8>,
+    mailbox: FragmentingMailbox,
+    received: Option<Vec<u8>>,
+}
+
+impl Host {
+    fn new(eth: EthernetAddress, neighbor: Option<EthernetAddress>, payload: Vec<u8>) -> Self {
+        Self {
+            eth,
+            neighbor,
+            payload,
+            mailbox: FragmentingMailbox::new(Duration::from_micros(1)),
+            received: None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Host {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic_with_mtu(self.eth, Some(10), MTU);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(neighbor) = self.neighbor {
+            nics.link(nics[0].id, &neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.neighbor.is_some() {
+            let payload = std::mem::take(&mut self.payload);
+            if !payload.is_empty() {
+                self.mailbox.send(mail, &nics[0], payload)?;
+            }
+        } else if self.received.is_none() {
+            let msg = self.mailbox.recv(mail).await;
+            self.received = Some(msg.into_data());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn fragments_are_reassembled() {
+    let payload: Vec<u8> = (0..3 * MTU).map(|i| i as u8).collect();
+    let mut sender = Host::new(ETH0, Some(ETH1), payload.clone());
+    let mut receiver = Host::new(ETH1, None, Vec::new());
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let stats = run_sim(nodes).expect("Sim runs to completion");
+
+    // Each 64 byte frame carries 52 bytes of the payload.
+    assert_eq!(stats.link(0).sent, 4);
+    assert_eq!(receiver.received, Some(payload));
+}
@@ -0,0 +1,62 @@
+use smoltcp::wire::{EthernetAddress, EthernetProtocol};
+
+use super::{ETH0, ETH1};
+use crate::message::frame::Frame;
+use crate::message::Mailbox;
+use crate::simulator::run_sim;
+use crate::{
+    nics::{NicAllocator, NicId, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Sends `frame` when the simulation starts if it has one, otherwise keeps the first it receives.
+struct Station {
+    eth: EthernetAddress,
+    frame: Option<Frame>,
+    received: Option<(NicId, Frame)>,
+}
+
+#[async_trait::async_trait]
+impl Node for Station {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(frame) = &self.frame {
+            nics.link(nics[0].id, &frame.dst_mac).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        match self.frame.take() {
+            Some(frame) => mail.send_frame(&nics[0], &frame)?,
+            None if self.received.is_none() => self.received = Some(mail.recv_frame().await),
+            None => {}
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn frame_round_trip() {
+    let frame = Frame::new(ETH0, ETH1, EthernetProtocol::Ipv4, b"payload".to_vec());
+    let mut sender = Station {
+        eth: ETH0,
+        frame: Some(frame.clone()),
+        received: None,
+    };
+    let mut receiver = Station {
+        eth: ETH1,
+        frame: None,
+        received: None,
+    };
+    let stats = run_sim(nodes![sender, receiver]).expect("Sim runs to completion");
+    assert_eq!(stats.link(0).bytes_received, frame.wire_len() as u64);
+    assert_eq!(receiver.received, Some((1, frame)));
+}
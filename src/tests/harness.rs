@@ -0,0 +1,56 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::Sender;
+use super::{ETH0, ETH1};
+use crate::message::Mailbox;
+use crate::testing::TestHarness;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Sends every message it receives back where it came from.
+struct Echo {
+    eth: EthernetAddress,
+}
+
+#[async_trait::async_trait]
+impl Node for Echo {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        let msg = mail.recv().await;
+        if let Some(nic) = nics.find_id(msg.to()) {
+            mail.send(nic, msg.into_data())?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn echo_is_delivered_both_ways() {
+    let nodes: &mut [&mut dyn Node] =
+        nodes![Sender::new(ETH0, ETH1).latency(10), Echo { eth: ETH1 }];
+    let mut harness = TestHarness::new(nodes).expect("Sim correctly initializes");
+    let there = harness.expect_delivery(0, 1, b"hello").at;
+    let back = harness.expect_delivery(1, 0, b"hello").at;
+    assert_eq!((there, back), (10, 20));
+    assert_eq!(harness.deliveries().len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "to reach node 0")]
+fn missing_delivery_panics() {
+    let nodes: &mut [&mut dyn Node] = nodes![Sender::new(ETH0, ETH1), Echo { eth: ETH1 }];
+    let mut harness = TestHarness::new(nodes).expect("Sim correctly initializes");
+    harness.expect_delivery(1, 0, b"goodbye");
+}
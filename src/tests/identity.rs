@@ -0,0 +1,127 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::Sender;
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::simulator::run_sim;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Remembers who it is told it is while processing.
+struct Introspector {
+    eth: EthernetAddress,
+    seen: Option<(usize, String)>,
+}
+
+impl Introspector {
+    fn new(eth: EthernetAddress) -> Self {
+        Self { eth, seen: None }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Introspector {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, _: &mut Mailbox, nics: &Nics<'_>) -> Result<Continue, NodeError> {
+        self.seen = Some((nics.node_id(), nics.node_name().to_string()));
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn instances_get_distinct_ids() {
+    let mut a = Introspector::new(ETH0);
+    let mut b = Introspector::new(ETH1);
+    let name = a.name().to_string();
+    run_sim(nodes![a, b]).expect("Sim runs to completion");
+
+    assert_eq!(a.seen, Some((0, name.clone())));
+    assert_eq!(b.seen, Some((1, name)));
+}
+
+/// What a protocol might keep about one of its interfaces.
+struct Port {
+    cost: u32,
+    admin_up: bool,
+}
+
+/// Attaches a `Port` to its nic, and reads it back while processing.
+struct Annotated {
+    seen: Option<u32>,
+}
+
+#[async_trait::async_trait]
+impl Node for Annotated {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        let port = Port {
+            cost: 10,
+            admin_up: true,
+        };
+        nics.nic_with_metadata(ETH0, None, port);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, _: &mut Mailbox, nics: &Nics<'_>) -> Result<Continue, NodeError> {
+        assert_eq!(nics[0].metadata::<String>(), None);
+        self.seen = nics[0]
+            .metadata::<Port>()
+            .filter(|port| port.admin_up)
+            .map(|port| port.cost);
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn nic_metadata_read_back_in_process() {
+    let mut annotated = Annotated { seen: None };
+    run_sim(nodes![annotated]).expect("Sim runs to completion");
+    assert_eq!(annotated.seen, Some(10));
+}
+
+/// Notes which node each message came from, by the nic it arrived on.
+struct Origins {
+    from: Vec<(u64, u64)>,
+}
+
+#[async_trait::async_trait]
+impl Node for Origins {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH2, None);
+        nics.nic(ETH3, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            let nic = nics
+                .find_id(msg.to())
+                .expect("mail arrives on one of the node's nics");
+            let sender = nic.partner_node().expect("the nic is linked");
+            self.from.push((nic.node(), sender));
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn received_frames_resolve_to_their_sender() {
+    let mut origins = Origins { from: Vec::new() };
+    let mut near = Sender::new(ETH0, ETH2);
+    let mut far = Sender::new(ETH1, ETH3).latency(10);
+    run_sim(nodes![origins, near, far]).expect("Sim runs to completion");
+    assert_eq!(origins.from, [(0, 1), (0, 2)]);
+}
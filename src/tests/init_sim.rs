@@ -1,20 +1,14 @@
 use smoltcp::wire::EthernetAddress;
 
+use super::{ETH0, ETH1, ETH2, ETH3};
 use crate::message::Mailbox;
-use crate::simulator::{run_sim, sim_setup};
+use crate::simulator::{run_sim, sim_setup, Sim, SimErr};
 use crate::{
-    nics::{NicAllocator, Nics, NicsMut},
-    node::{Node, NodeError},
+    nics::{NicAllocator, NicError, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
     nodes,
 };
 
-const ETH0: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 0]);
-const ETH1: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 1]);
-const ETH2: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 2]);
-const ETH3: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 3]);
-const ETH4: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 4]);
-const ETH5: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 5]);
-
 struct BasicNode {
     eth: Option<EthernetAddress>,
     neighbor: Option<EthernetAddress>,
@@ -55,17 +49,128 @@ impl Node for BasicNode {
         }
     }
 
-    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<(), NodeError> {
-        Ok(())
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+fn assert_no_hardware(err: SimErr, index: usize) {
+    let SimErr::NodeNoHardware { node, name } = err else {
+        panic!("Expected NodeNoHardware, got {err:?}");
+    };
+    assert_eq!(node, index);
+    assert!(name.ends_with("BasicNode"));
+}
+
+/// Links to its neighbor, reporting failure rather than panicking.
+struct Linker {
+    eth: EthernetAddress,
+    neighbor: EthernetAddress,
+}
+
+#[async_trait::async_trait]
+impl Node for Linker {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
     }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    fn try_startup(&mut self, nics: &mut NicsMut<'_>) -> Result<(), NicError> {
+        nics.link(nics[0].id, &self.neighbor)
+    }
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn sim_setup_unresolved_neighbor() {
+    const MISSING: EthernetAddress = EthernetAddress([2, 0, 0, 0, 0, 9]);
+    let err = sim_setup(nodes![
+        BasicNode::new(ETH0),
+        Linker {
+            eth: ETH1,
+            neighbor: MISSING
+        }
+    ])
+    .expect_err("Neighbor does not exist");
+    assert!(matches!(
+        err,
+        SimErr::UnresolvedNeighbor {
+            node: 1,
+            mac: MISSING
+        }
+    ));
+}
+
+#[test]
+fn add_node_unresolved_neighbor() {
+    let mut node = BasicNode::new(ETH0);
+    let nodes: &mut [&mut dyn Node] = nodes![node];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    let err = sim
+        .add_node(Box::new(Linker {
+            eth: ETH1,
+            neighbor: ETH2,
+        }))
+        .expect_err("Neighbor does not exist");
+    assert!(matches!(err, SimErr::UnresolvedNeighbor { node: 1, .. }));
+    assert_eq!(sim.topology().all_nics().len(), 1);
+
+    // The failed node left nothing behind.
+    let node = sim
+        .add_node(Box::new(Linker {
+            eth: ETH1,
+            neighbor: ETH0,
+        }))
+        .expect("Node is added");
+    assert_eq!(node, 1);
+    assert_eq!(sim.topology.links.len(), 1);
 }
 
 #[test]
 fn sim_setup_failure_cases() {
-    run_sim(nodes![BasicNode::broken()]).expect_err("Node should have at least one NIC");
+    let err = run_sim(nodes![BasicNode::broken()]).expect_err("Node should have at least one NIC");
+    assert_no_hardware(err, 0);
 
-    run_sim(nodes![BasicNode::new(ETH0), BasicNode::broken()])
+    let err = run_sim(nodes![BasicNode::new(ETH0), BasicNode::broken()])
         .expect_err("Node should have at least one NIC");
+    assert_no_hardware(err, 1);
+}
+
+#[test]
+fn sim_setup_reports_node_without_hardware() {
+    let err = sim_setup(nodes![
+        BasicNode::new(ETH0),
+        BasicNode::broken(),
+        BasicNode::new(ETH1)
+    ])
+    .expect_err("Node should have at least one NIC");
+    assert_no_hardware(err, 1);
+
+    let err = sim_setup(nodes![
+        BasicNode::new(ETH0),
+        BasicNode::new(ETH1),
+        BasicNode::broken(),
+        BasicNode::broken()
+    ])
+    .expect_err("Node should have at least one NIC");
+    assert_no_hardware(err, 2);
+}
+
+#[test]
+fn sim_build_bundles_mailboxes() {
+    let mut a = BasicNode::new(ETH0).set_neighbor(ETH1);
+    let mut b = BasicNode::new(ETH1);
+    let mut c = BasicNode::new(ETH2);
+    let nodes: &mut [&mut dyn Node] = nodes![a, b, c];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    assert_eq!(sim.topology().node_count(), 3);
+    assert_eq!(sim.mailboxes.len(), sim.topology().node_count());
+    assert_eq!(sim.topology().links.len(), 1);
+    sim.run().expect("Sim runs to completion");
 }
 
 #[test]
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use smoltcp::wire::{EthernetAddress, Ipv4Address, Ipv4Cidr, Ipv4Packet};
+
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::node::ip::{self, IpNode};
+use crate::simulator::{run_sim, sim_setup};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// A host on a subnet, linked to its gateway.
+/// Sends a packet to `target` when the simulation starts, and keeps every packet it receives.
+struct Host {
+    eth: EthernetAddress,
+    ip: Ipv4Cidr,
+    gateway: EthernetAddress,
+    target: Option<Ipv4Address>,
+    received: Vec<Vec<u8>>,
+}
+
+impl Host {
+    fn new(eth: EthernetAddress, ip: Ipv4Cidr, gateway: EthernetAddress) -> Self {
+        Self {
+            eth,
+            ip,
+            gateway,
+            target: None,
+            received: Vec::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Host {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic_with_ip(self.eth, Some(5), self.ip);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.gateway).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if let Some(target) = self.target.take() {
+            mail.send(
+                &nics[0],
+                ip::packet(self.ip.address(), target, 64, b"across"),
+            )?;
+        }
+        while let Some(msg) = mail.try_recv() {
+            self.received.push(msg.into_data());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+fn cidr(subnet: u8, host: u8) -> Ipv4Cidr {
+    Ipv4Cidr::new(Ipv4Address::new(10, 0, subnet, host), 24)
+}
+
+#[test]
+fn packet_routed_between_subnets() {
+    let mut a = Host::new(ETH0, cidr(1, 2), ETH1);
+    a.target = Some(Ipv4Address::new(10, 0, 2, 2));
+    let mut router = IpNode::new(vec![(ETH1, cidr(1, 1)), (ETH2, cidr(2, 1))]);
+    let mut b = Host::new(ETH3, cidr(2, 2), ETH2);
+    let stats = run_sim(nodes![a, router, b]).expect("Sim runs to completion");
+
+    assert_eq!(stats.node(1).sent, 1);
+    assert!(a.received.is_empty());
+    assert!(router.received().is_empty());
+    let [packet] = &b.received[..] else {
+        panic!("B should receive exactly one packet");
+    };
+    let packet = Ipv4Packet::new_checked(&packet[..]).expect("Packet is well formed");
+    assert!(packet.verify_checksum());
+    assert_eq!(packet.src_addr(), Ipv4Address::new(10, 0, 1, 2));
+    assert_eq!(packet.hop_limit(), 63);
+    assert_eq!(packet.payload(), b"across");
+}
+
+#[test]
+fn route_over_an_unlinked_port_drops_the_packet() {
+    let mut a = Host::new(ETH0, cidr(1, 2), ETH1);
+    a.target = Some(Ipv4Address::new(10, 0, 2, 2));
+    // Nothing is plugged into the router's port on subnet 2.
+    let mut router = IpNode::new(vec![(ETH1, cidr(1, 1)), (ETH2, cidr(2, 1))]);
+    let stats = run_sim(nodes![a, router]).expect("Sim runs to completion");
+
+    assert_eq!(stats.node(1).received, 1);
+    assert_eq!(stats.node(1).sent, 0);
+}
+
+#[test]
+fn longest_prefix_wins() {
+    let mut router = IpNode::new(vec![
+        (ETH0, Ipv4Cidr::new(Ipv4Address::new(10, 0, 0, 1), 8)),
+        (ETH1, Ipv4Cidr::new(Ipv4Address::new(10, 0, 7, 1), 24)),
+    ]);
+    let topology = sim_setup(nodes![router]).expect("Sim correctly initializes");
+    let nics = Nics::from_slice(topology.nics(0));
+    let route = |dst| ip::longest_prefix_match(&nics, &dst);
+    assert_eq!(route(Ipv4Address::new(10, 0, 7, 9)), Some(1));
+    assert_eq!(route(Ipv4Address::new(10, 9, 9, 9)), Some(0));
+    assert_eq!(route(Ipv4Address::new(192, 168, 0, 1)), None);
+}
+
+/// Two nics on one subnet, each linked to a port of the same neighbor.
+struct DualHomed;
+
+#[async_trait::async_trait]
+impl Node for DualHomed {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic_with_ip(ETH2, None, cidr(9, 3));
+        nics.nic_with_ip(ETH3, None, cidr(9, 4));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH0).unwrap();
+        nics.link(nics[1].id, &ETH1).unwrap();
+    }
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn flows_stick_to_one_of_parallel_links() {
+    let mut router = IpNode::new(vec![(ETH0, cidr(9, 1)), (ETH1, cidr(9, 2))]);
+    let topology = sim_setup(nodes![router, DualHomed]).expect("Sim correctly initializes");
+    let nics = Nics::from_slice(topology.nics(0));
+    let dst = Ipv4Address::new(10, 0, 9, 3);
+    let egress = |flow: u16| {
+        nics.select_egress(&dst, &flow.to_be_bytes())
+            .expect("Both links reach the subnet")
+            .id
+    };
+
+    for flow in 0..16 {
+        assert_eq!(egress(flow), egress(flow));
+    }
+    let used: HashSet<_> = (0..16).map(egress).collect();
+    assert_eq!(used, HashSet::from([0, 1]));
+    assert!(nics
+        .select_egress(&Ipv4Address::new(10, 0, 8, 1), b"flow")
+        .is_none());
+}
@@ -0,0 +1,668 @@
+use std::time::Duration;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::message::Mailbox;
+use crate::rng::Rng;
+use crate::simulator::{sim_setup, LinkForwarding, LinkProfile, Sim};
+use crate::{
+    nics::{NicAllocator, NicError, NicId, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Links with its neighbor, then changes its mind.
+struct Flaky {
+    eth: EthernetAddress,
+    neighbor: EthernetAddress,
+}
+
+#[async_trait::async_trait]
+impl Node for Flaky {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.neighbor).unwrap();
+        nics.unlink(nics[0].id).unwrap();
+    }
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Sends a message to its neighbor every 100ns, `count` times.
+struct Beacon {
+    eth: EthernetAddress,
+    neighbor: EthernetAddress,
+    count: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for Beacon {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.neighbor).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while self.count > 0 {
+            self.count -= 1;
+            mail.send(&nics[0], b"beep".to_vec())?;
+            mail.recv_timeout(Duration::from_nanos(100)).await;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn disabled_nic_drops_until_enabled() {
+    let mut beacon = Beacon {
+        eth: ETH0,
+        neighbor: ETH1,
+        count: 2,
+    };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![beacon, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    assert!(sim.set_enabled(1, false));
+    sim.run_until(50).expect("Sim runs to its limit");
+    assert_eq!(sim.stats().link(0).dropped, 1);
+    assert_eq!(sim.stats().link(0).received, 0);
+    // The link survives
+    assert!(sim.topology.nics(1)[0].link_id.is_some());
+
+    assert!(sim.set_enabled(1, true));
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().link(0).dropped, 1);
+    assert_eq!(sim.stats().link(0).received, 1);
+    drop(sim);
+    assert_eq!(receiver.received, 1);
+}
+
+/// Links each of its nics to the node at the same position in `targets`.
+struct Connector {
+    targets: Vec<usize>,
+    results: Vec<Result<(), NicError>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Connector {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for _ in &self.targets {
+            nics.nic_auto(None);
+        }
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        for (i, target) in self.targets.iter().enumerate() {
+            let result = nics.connect_to_node(nics[i].id, *target);
+            self.results.push(result);
+        }
+    }
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn connect_by_node_index() {
+    let mut connector = Connector {
+        targets: vec![1, 1, 7],
+        results: Vec::new(),
+    };
+    let mut receiver = Receiver::new(ETH0);
+    let topology = sim_setup(nodes![connector, receiver]).expect("Sim correctly initializes");
+
+    assert_eq!(topology.links.len(), 1);
+    assert_eq!(topology.links[0], (0u64, 3u64));
+    assert!(topology.nics(1)[0].link_id.is_some());
+    assert!(matches!(
+        connector.results[..],
+        [
+            Ok(()),
+            Err(NicError::NoFreeNic),
+            Err(NicError::NeighborNotFound)
+        ]
+    ));
+}
+
+/// Links its only nic to its own MAC.
+struct Looped {
+    result: Option<Result<(), NicError>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Looped {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        self.result = Some(nics.link(nics[0].id, &ETH0));
+    }
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn linking_to_own_mac_fails() {
+    let mut looped = Looped { result: None };
+    let topology = sim_setup(nodes![looped]).expect("Sim correctly initializes");
+    assert!(topology.links.is_empty());
+    assert!(matches!(looped.result, Some(Err(NicError::SelfLink))));
+}
+
+#[test]
+fn unlink_stops_delivery() {
+    let mut sender = Sender::new(ETH0, ETH1);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+
+    let link = sim.topology.links[0].id;
+    assert_eq!(sim.topology.unlink(link).unwrap(), (0u64, 1u64));
+    assert!(sim.topology.links.is_empty());
+    assert!(sim.topology.nics(0)[0].link_id.is_none());
+    assert!(sim.topology.nics(1)[0].link_id.is_none());
+
+    let nic = sim.topology.nics(0)[0].clone();
+    assert_eq!(
+        sim.mailboxes[0].send(&nic, b"hello again".to_vec()),
+        Err(NodeError::LinkDown)
+    );
+    drop(sim);
+    assert_eq!(receiver.received, 1);
+}
+
+#[test]
+fn unlink_drops_messages_in_flight() {
+    let mut sender = Sender::new(ETH4, ETH5).latency(100);
+    let mut receiver = Receiver::new(ETH5);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let mut sim = Sim::new(nodes, topology);
+    // The sender's message is now in flight.
+    sim.start().expect("Nodes start");
+
+    let link = sim.topology.links[0].id;
+    sim.topology.unlink(link).expect("Link exists");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().link(link).dropped, 1);
+    assert_eq!(sim.stats().node(0).dropped, 1);
+    drop(sim);
+    assert_eq!(receiver.received, 0);
+}
+
+#[test]
+fn unlink_during_startup() {
+    let topology = sim_setup(nodes![
+        Flaky {
+            eth: ETH2,
+            neighbor: ETH3
+        },
+        Receiver::new(ETH3)
+    ])
+    .expect("Sim correctly initializes");
+    assert!(topology.links.is_empty());
+    assert!(topology.nics(0)[0].link_id.is_none());
+}
+
+/// Waits on its mailbox for one message, then tries to send it back.
+struct Replier {
+    result: Option<Result<(), NodeError>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Replier {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH1).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        let msg = mail.recv().await;
+        self.result = Some(mail.send(&nics[0], msg.into_data()));
+        Ok(Continue::Done)
+    }
+}
+
+#[test]
+fn send_sees_unlink_while_waiting_on_recv() {
+    let mut replier = Replier { result: None };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![replier, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    // The replier is now waiting in `recv`, holding its nics from before the link went down.
+    sim.start().expect("Nodes start");
+
+    let link = sim.topology().link_between(0, 1).expect("Nodes are linked");
+    sim.unlink(link).expect("Link exists");
+    sim.inject(0, 1, b"ping".to_vec());
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(0).dropped, 0);
+    drop(sim);
+    assert_eq!(replier.result, Some(Err(NodeError::LinkDown)));
+    assert_eq!(receiver.received, 0);
+}
+
+/// Sends two frames at once, reading the link's utilization as they drain.
+struct Saturator {
+    utilization: Vec<f64>,
+}
+
+#[async_trait::async_trait]
+impl Node for Saturator {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH1).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.utilization.is_empty() {
+            mail.send(&nics[0], vec![0; 125])?;
+            mail.send(&nics[0], vec![1; 125])?;
+            // Frames only reach the link once `process` yields, so look 1ns later.
+            for wait in [1, 1_499_999, 1_000_000] {
+                mail.recv_timeout(Duration::from_nanos(wait)).await;
+                self.utilization.push(mail.link_utilization(nics[0].id));
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn utilization_of_a_saturated_link() {
+    let mut saturator = Saturator {
+        utilization: Vec::new(),
+    };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![saturator, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    // Each frame takes a millisecond to send.
+    assert!(topology.set_bandwidth(link, Some(1_000_000)));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 2_500_000);
+    assert_eq!(sim.stats().link(0).received, 2);
+    drop(sim);
+    assert_eq!(saturator.utilization, [1.0, 0.5, 0.0]);
+}
+
+#[test]
+fn linked_nics_yield_partners() {
+    let mut connector = Connector {
+        targets: vec![1, 2, 3, 9],
+        results: Vec::new(),
+    };
+    let topology = sim_setup(nodes![
+        connector,
+        Receiver::new(ETH0),
+        Receiver::new(ETH1),
+        Receiver::new(ETH2)
+    ])
+    .expect("Sim correctly initializes");
+
+    let nics = Nics::from_slice(topology.nics(0));
+    let linked: Vec<_> = nics
+        .linked_nics()
+        .map(|(nic, partner)| (nic.id, partner))
+        .collect();
+    assert_eq!(linked, [(0, 4), (1, 5), (2, 6)]);
+}
+
+#[test]
+fn link_partner_of_a_pair() {
+    let topology = sim_setup(nodes![
+        Sender::new(ETH0, ETH1),
+        Receiver::new(ETH1).nic(ETH2)
+    ])
+    .expect("Sim correctly initializes");
+    assert_eq!(topology.link_partner(0), Some(1));
+    assert_eq!(topology.link_partner(1), Some(0));
+    assert_eq!(topology.link_partner(2), None);
+    assert_eq!(topology.link_partner(9), None);
+}
+
+#[test]
+fn random_neighbor_is_repeatable() {
+    let mut connector = Connector {
+        targets: vec![1, 2, 3, 4],
+        results: Vec::new(),
+    };
+    let topology = sim_setup(nodes![
+        connector,
+        Receiver::new(ETH0),
+        Receiver::new(ETH1),
+        Receiver::new(ETH2),
+        Receiver::new(ETH3)
+    ])
+    .expect("Sim correctly initializes");
+    let nics = Nics::from_slice(topology.nics(0));
+    let picks = |seed| {
+        let mut rng = Rng::new(seed);
+        let mut counts = [0; 4];
+        for _ in 0..400 {
+            let (nic, _) = nics.random_neighbor(&mut rng).expect("Node has links");
+            counts[nic.id as usize] += 1;
+        }
+        counts
+    };
+    let counts = picks(7);
+    assert_eq!(counts, picks(7));
+    assert_ne!(counts, picks(8));
+    assert_eq!(counts.iter().sum::<i32>(), 400);
+    assert!(counts.iter().all(|&count| (70..130).contains(&count)));
+    assert!(Nics::from_slice(&[])
+        .random_neighbor(&mut Rng::new(7))
+        .is_none());
+}
+
+/// When a 1000 byte frame from a node with 100ns of latency arrives over a one gigabit link.
+fn arrival(forwarding: LinkForwarding) -> u64 {
+    let mut sender = Sender::new(ETH0, ETH1).size(1000).latency(100);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_bandwidth(link, Some(1_000_000_000)));
+    assert!(topology.set_forwarding(link, forwarding));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().link(0).received, 1);
+    sim.now()
+}
+
+#[test]
+fn cut_through_arrives_after_the_header() {
+    // 8ns a byte: the whole frame takes 8000ns to send, its header 112ns.
+    assert_eq!(arrival(LinkForwarding::StoreAndForward), 8100);
+    assert_eq!(arrival(LinkForwarding::CutThrough), 212);
+}
+
+/// Links to its neighbor over a satellite hop, then sends it one message.
+struct Uplink {
+    sent: bool,
+}
+
+#[async_trait::async_trait]
+impl Node for Uplink {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, Some(100));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link_with_profile(nics[0].id, &ETH1, LinkProfile::Satellite)
+            .unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if !self.sent {
+            self.sent = true;
+            mail.send(&nics[0], b"hello".to_vec())?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn satellite_profile_sets_delivery_time() {
+    let mut uplink = Uplink { sent: false };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![uplink, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    // 300ms to orbit and back, after 1600ns sending 5 bytes at 25Mbps. The nic's own latency is ignored.
+    assert_eq!(sim.now(), 300_001_600);
+    drop(sim);
+    assert_eq!(receiver.received, 1);
+}
+
+/// Passes everything from its first nic on out of its second, taking `delay` to do so.
+struct Relay {
+    delay: u64,
+}
+
+#[async_trait::async_trait]
+impl Node for Relay {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+        nics.nic(ETH2, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[1].id, &ETH3).unwrap();
+    }
+
+    fn processing_delay(&self) -> u64 {
+        self.delay
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            mail.forward(msg, &nics[1])?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// When a message sent over 10ns of latency, through a relay taking `delay`, reaches the far end.
+fn relayed_arrival(delay: u64) -> u64 {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10);
+    let mut relay = Relay { delay };
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, relay, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(2).received, 1);
+    sim.now()
+}
+
+#[test]
+fn processing_delay_adds_to_the_path() {
+    assert_eq!(relayed_arrival(0), 10);
+    assert_eq!(relayed_arrival(50), 60);
+}
+
+/// Sends 40 frames from each of flows 1 and 2 at once, every frame marked with its flow.
+struct TwoFlows;
+
+#[async_trait::async_trait]
+impl Node for TwoFlows {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH1).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        for flow in [1, 2] {
+            for _ in 0..40 {
+                mail.send_with_flow(&nics[0], vec![flow as u8; 100], flow)?;
+            }
+        }
+        Ok(Continue::Done)
+    }
+}
+
+/// How many of the first 40 frames delivered over a saturated link came from each of the two flows.
+fn first_forty(weights: Option<Vec<(u32, u32)>>) -> (usize, usize) {
+    let mut sender = TwoFlows;
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_bandwidth(link, Some(8_000_000)));
+    assert!(topology.set_flow_weights(link, weights));
+    let mut sim = Sim::new(nodes, topology);
+    sim.record();
+    sim.run().expect("Sim runs to completion");
+    let recorder = sim.take_recorder().expect("Sim is recording");
+    assert_eq!(recorder.deliveries().len(), 80);
+    let first = &recorder.deliveries()[..40];
+    let count = |flow| first.iter().filter(|d| d.data[0] == flow).count();
+    (count(1), count(2))
+}
+
+#[test]
+fn weighted_fair_link_splits_bandwidth_by_weight() {
+    // First in, first out, the second flow waits for all of the first.
+    assert_eq!(first_forty(None), (40, 0));
+    assert_eq!(first_forty(Some(vec![(1, 3), (2, 1)])), (30, 10));
+}
+
+/// Records every link change it hears of.
+struct Watcher {
+    eth: EthernetAddress,
+    changes: Vec<(NicId, bool)>,
+}
+
+#[async_trait::async_trait]
+impl Node for Watcher {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn on_link_change(&mut self, nic: NicId, up: bool, _: &Nics<'_>) {
+        self.changes.push((nic, up));
+    }
+
+    async fn process(&mut self, _: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn disabling_a_link_tells_the_node_on_it() {
+    let mut sender = Sender::new(ETH0, ETH1);
+    let mut watcher = Watcher {
+        eth: ETH1,
+        changes: Vec::new(),
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, watcher];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+
+    assert!(sim.set_enabled(1, false));
+    // Disabling the other end too leaves the link just as down.
+    assert!(sim.set_enabled(0, false));
+    sim.run().expect("Sim runs to completion");
+    assert!(sim.set_enabled(0, true));
+    assert!(sim.set_enabled(1, true));
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    assert_eq!(watcher.changes, [(1, false), (1, true)]);
+}
+
+/// Waits on its mailbox for messages, recording every link change it hears of
+/// with how many it had received by then.
+#[derive(Default)]
+struct Waiter {
+    changes: Vec<(NicId, bool, usize)>,
+    received: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for Waiter {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH0).unwrap();
+    }
+
+    async fn on_link_change(&mut self, nic: NicId, up: bool, _: &Nics<'_>) {
+        self.changes.push((nic, up, self.received));
+    }
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        mail.recv().await;
+        self.received += 1;
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn node_waiting_in_process_hears_of_link_change_once_it_returns() {
+    let mut receiver = Receiver::new(ETH0);
+    let mut waiter = Waiter::default();
+    let nodes: &mut [&mut dyn Node] = nodes![receiver, waiter];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    // The waiter is still in its first `process`, waiting in `recv`.
+    let link = sim.topology().link_between(0, 1).expect("Nodes are linked");
+    sim.unlink(link).expect("Link exists");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(1).received, 0);
+
+    sim.inject(1, 0, b"wake up".to_vec());
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    // Heard only after the message that let `process` return.
+    assert_eq!(waiter.changes, [(1, false, 1)]);
+}
+
+#[test]
+fn link_ids_can_be_looked_up() {
+    let mut sender = Sender::new(ETH0, ETH1);
+    let mut receiver = Receiver::new(ETH1);
+    let mut lone = Receiver::new(ETH2);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver, lone];
+    let topology = sim_setup(nodes).expect("Sim correctly initializes");
+
+    let link = topology.link_between(0, 1);
+    assert!(link.is_some());
+    assert_eq!(topology.link_between(1, 0), link);
+    assert_eq!(topology.link_between(0, 2), None);
+    assert_eq!(topology.all_nics()[0].link_id(), link);
+    assert_eq!(topology.all_nics()[2].link_id(), None);
+}
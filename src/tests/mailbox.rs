@@ -0,0 +1,482 @@
+use std::time::Duration;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::message::{IncomingMsg, Mailbox};
+use crate::simulator::{event::SimEvent, sim_setup, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Waits for one message when the simulation starts, giving up after `timeout`.
+struct Waiter {
+    eth: EthernetAddress,
+    timeout: Duration,
+    received: Option<Option<Vec<u8>>>,
+    /// `Mailbox::now` once the wait is over.
+    woken_at: Option<u64>,
+}
+
+impl Waiter {
+    fn new(eth: EthernetAddress, timeout: Duration) -> Self {
+        Self {
+            eth,
+            timeout,
+            received: None,
+            woken_at: None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Waiter {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        if self.received.is_none() {
+            let msg = mail.recv_timeout(self.timeout).await;
+            self.received = Some(msg.map(|msg| msg.into_data()));
+            self.woken_at = Some(mail.now());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Broadcasts a message when the simulation starts, from nics linked to each of `neighbors`.
+struct Broadcaster {
+    neighbors: Vec<(EthernetAddress, EthernetAddress)>,
+}
+
+#[async_trait::async_trait]
+impl Node for Broadcaster {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for (eth, _) in &self.neighbors {
+            nics.nic(*eth, None);
+        }
+        // Left unlinked
+        nics.nic(EthernetAddress([2, 0, 0, 0, 0, 0]), None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        for (i, (_, neighbor)) in self.neighbors.iter().enumerate() {
+            nics.link(nics[i].id, neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        mail.broadcast(b"flood".to_vec(), nics);
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Sends one message of each size in `sizes` when the simulation starts, keeping the results.
+struct SizedSender {
+    eth: EthernetAddress,
+    neighbor: EthernetAddress,
+    mtu: usize,
+    sizes: Vec<usize>,
+    results: Vec<Result<(), NodeError>>,
+}
+
+#[async_trait::async_trait]
+impl Node for SizedSender {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic_with_mtu(self.eth, None, self.mtu);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.neighbor).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        for size in &self.sizes {
+            self.results.push(mail.send(&nics[0], vec![0; *size]));
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn send_enforces_mtu() {
+    let mut sender = SizedSender {
+        eth: ETH0,
+        neighbor: ETH1,
+        mtu: 64,
+        sizes: vec![64, 65],
+        results: Vec::new(),
+    };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().link(0).received, 1);
+    drop(sim);
+    assert_eq!(sender.results, vec![Ok(()), Err(NodeError::FrameTooLarge)]);
+}
+
+#[test]
+fn recv_timeout_expires() {
+    let mut waiter = Waiter::new(ETH0, Duration::from_nanos(500));
+    let nodes: &mut [&mut dyn Node] = nodes![waiter];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+
+    // Nothing arrives, so the only event is the timeout.
+    assert_eq!(
+        sim.step().expect("Timeout fires"),
+        Some(SimEvent::Woken { node: 0, at: 500 })
+    );
+    assert_eq!(sim.now(), 500);
+    assert_eq!(sim.step().expect("Nothing left to do"), None);
+    drop(sim);
+    assert_eq!(waiter.received, Some(None));
+}
+
+#[test]
+fn recv_timeout_receives() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(100);
+    let mut waiter = Waiter::new(ETH1, Duration::from_nanos(500));
+    let nodes: &mut [&mut dyn Node] = nodes![sender, waiter];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+
+    assert!(matches!(
+        sim.step().expect("Message is delivered"),
+        Some(SimEvent::Delivered { at: 100, .. })
+    ));
+    assert_eq!(sim.now(), 100);
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    assert_eq!(waiter.received, Some(Some(b"hello".to_vec())));
+}
+
+#[test]
+fn broadcast_reaches_every_link() {
+    let mut broadcaster = Broadcaster {
+        neighbors: vec![(ETH0, ETH3), (ETH1, ETH4), (ETH2, ETH5)],
+    };
+    let mut receivers = [
+        Receiver::new(ETH3),
+        Receiver::new(ETH4),
+        Receiver::new(ETH5),
+    ];
+    let [a, b, c] = &mut receivers;
+    let nodes: &mut [&mut dyn Node] = nodes![broadcaster, *a, *b, *c];
+    Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .run()
+        .expect("Sim runs to completion");
+
+    assert!(receivers.iter().all(|receiver| receiver.received == 1));
+}
+
+#[test]
+fn now_reads_delivery_time() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(300);
+    let mut waiter = Waiter::new(ETH3, Duration::from_nanos(1000));
+    let nodes: &mut [&mut dyn Node] = nodes![sender, waiter];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    assert_eq!(waiter.received, Some(Some(b"hello".to_vec())));
+    assert_eq!(waiter.woken_at, Some(300));
+}
+
+/// Records whether each message it receives is valid.
+struct Checker {
+    eth: EthernetAddress,
+    valid: Vec<bool>,
+}
+
+#[async_trait::async_trait]
+impl Node for Checker {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            self.valid.push(msg.is_valid());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Whether each of three messages arrives valid over a link with `bit_error_rate`.
+fn validity(bit_error_rate: f64) -> Vec<bool> {
+    let mut sender = Sender::new(ETH4, ETH5).count(3);
+    let mut checker = Checker {
+        eth: ETH5,
+        valid: Vec::new(),
+    };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, checker];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_bit_error_rate(link, bit_error_rate));
+    Sim::new(nodes, topology)
+        .run()
+        .expect("Sim runs to completion");
+    checker.valid
+}
+
+#[test]
+fn checksum_detects_corruption() {
+    assert_eq!(validity(0.0), [true; 3]);
+    // Every byte has a bit flipped.
+    assert_eq!(validity(1.0), [false; 3]);
+}
+
+#[test]
+fn same_time_frames_keep_send_order() {
+    let mut sender = SizedSender {
+        eth: ETH0,
+        neighbor: ETH1,
+        mtu: 1500,
+        sizes: vec![3, 1, 2],
+        results: Vec::new(),
+    };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut deliveries = Vec::new();
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.on_event(|event| {
+        if let SimEvent::Delivered { bytes, at, .. } = event {
+            deliveries.push((*bytes, *at));
+        }
+    });
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    // The link has no latency, so all three are due at once.
+    assert_eq!(deliveries, [(3, 0), (1, 0), (2, 0)]);
+}
+
+/// Sends three messages with a ten byte window, recording when each goes out.
+struct Windowed {
+    sent_at: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Node for Windowed {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, Some(100));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH1).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while self.sent_at.len() < 3 {
+            mail.send_backpressured(&nics[0], b"hello".to_vec(), 10)
+                .await?;
+            self.sent_at.push(mail.now());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn send_backpressured_waits_for_window() {
+    let mut sender = Windowed {
+        sent_at: Vec::new(),
+    };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run_until(50).expect("Sim runs to its limit");
+    assert_eq!(sim.mailboxes[0].in_flight_bytes(0), 10);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.mailboxes[0].in_flight_bytes(0), 0);
+    drop(sim);
+    // The third waits for the first two to land.
+    assert_eq!(sender.sent_at, [0, 0, 100]);
+    assert_eq!(receiver.received, 3);
+}
+
+/// Sends two 125 byte frames one after the other, recording when each has finished leaving.
+struct Pipelined {
+    sent_at: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Node for Pipelined {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, Some(100));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH1).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while self.sent_at.len() < 2 {
+            mail.send_and_wait(&nics[0], vec![0; 125]).await?;
+            self.sent_at.push(mail.now());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn send_and_wait_resolves_when_transmitted() {
+    let mut sender = Pipelined {
+        sent_at: Vec::new(),
+    };
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    // Each frame takes a millisecond to send.
+    assert!(topology.set_bandwidth(link, Some(1_000_000)));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 2_000_100);
+    drop(sim);
+    // Latency only delays arrival, not when the frame has left.
+    assert_eq!(sender.sent_at, [1_000_000, 2_000_000]);
+    assert_eq!(receiver.received, 2);
+}
+
+#[test]
+fn injected_frame_reaches_a_lone_node() {
+    let mut waiter = Waiter::new(ETH0, Duration::from_nanos(500));
+    let nodes: &mut [&mut dyn Node] = nodes![waiter];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    // No nic in the simulation has id 99.
+    sim.inject(0, 99, b"crafted".to_vec());
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(0).dropped, 0);
+    drop(sim);
+    assert_eq!(waiter.received, Some(Some(b"crafted".to_vec())));
+    // Well before the wait would have timed out.
+    assert_eq!(waiter.woken_at, Some(0));
+}
+
+/// Takes commands from its mailbox, noting when each was taken.
+struct Commands {
+    taken: Vec<(u64, Vec<u8>)>,
+}
+
+#[async_trait::async_trait]
+impl Node for Commands {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        while self.taken.len() < 2 {
+            let msg = mail.recv().await;
+            self.taken.push((mail.now(), msg.data().to_vec()));
+        }
+        Ok(Continue::Done)
+    }
+}
+
+#[test]
+fn initial_messages_are_taken_at_the_start() {
+    let mut commands = Commands { taken: Vec::new() };
+    let nodes: &mut [&mut dyn Node] = nodes![commands];
+    let seeded = vec![
+        IncomingMsg::injected(99, 0, b"first".to_vec()),
+        IncomingMsg::injected(99, 0, b"second".to_vec()),
+    ];
+    let mut sim = Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .with_initial_messages(0, seeded);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 0);
+    drop(sim);
+    assert_eq!(
+        commands.taken,
+        [(0, b"first".to_vec()), (0, b"second".to_vec())]
+    );
+}
+
+/// Sends one frame to `dst` by MAC alone, from a node linked to ETH2 and ETH3.
+struct Dialer {
+    dst: EthernetAddress,
+    result: Option<Result<(), NodeError>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Dialer {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH0, None);
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH2).unwrap();
+        nics.link(nics[1].id, &ETH3).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.result.is_none() {
+            self.result = Some(mail.send_to_mac(self.dst, b"dial".to_vec(), nics));
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn send_to_mac_picks_the_nic_linked_to_it() {
+    let mut dialer = Dialer {
+        dst: ETH3,
+        result: None,
+    };
+    let mut near = Receiver::new(ETH2);
+    let mut far = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![dialer, near, far];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    assert_eq!(dialer.result, Some(Ok(())));
+    assert_eq!(near.received, 0);
+    assert_eq!(far.received, 1);
+}
+
+#[test]
+fn send_to_mac_fails_when_no_nic_reaches_it() {
+    let mut dialer = Dialer {
+        dst: ETH5,
+        result: None,
+    };
+    let mut near = Receiver::new(ETH2);
+    let mut far = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![dialer, near, far];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    assert_eq!(dialer.result, Some(Err(NodeError::Unreachable)));
+    assert_eq!(near.received + far.received, 0);
+}
@@ -1 +1,41 @@
+use smoltcp::wire::EthernetAddress;
+
+mod ack;
+mod add_node;
+mod arp;
+mod barrier;
+mod device;
+mod duplex;
+mod events;
+#[cfg(feature = "host-io")]
+mod external;
+mod filter;
+mod fragment;
+mod frame;
+mod harness;
+mod identity;
 mod init_sim;
+mod ip;
+mod links;
+mod mailbox;
+mod multicast;
+mod nodes;
+mod probe;
+mod realtime;
+mod recorder;
+mod reset;
+mod router;
+mod scale;
+mod scheduler;
+mod sequence;
+mod stats;
+mod switch;
+mod termination;
+mod topology;
+
+const ETH0: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 0]);
+const ETH1: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 1]);
+const ETH2: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 2]);
+const ETH3: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 3]);
+const ETH4: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 4]);
+const ETH5: EthernetAddress = EthernetAddress([0, 0, 0, 0, 0, 5]);
@@ -0,0 +1,93 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::message::Mailbox;
+use crate::simulator::run_sim;
+use crate::{
+    nics::{MulticastGroup, NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+const GROUP: MulticastGroup = MulticastGroup(5);
+
+/// Multicasts to `GROUP` when the simulation starts, from nics linked to each of `neighbors`.
+struct Multicaster {
+    neighbors: Vec<(EthernetAddress, EthernetAddress)>,
+}
+
+#[async_trait::async_trait]
+impl Node for Multicaster {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for (eth, _) in &self.neighbors {
+            nics.nic(*eth, None);
+        }
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        for (i, (_, neighbor)) in self.neighbors.iter().enumerate() {
+            nics.link(nics[i].id, neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        mail.send_multicast(GROUP, b"group".to_vec(), nics);
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Counts the messages it receives, after joining `GROUP` if `member`.
+struct Member {
+    eth: EthernetAddress,
+    member: bool,
+    received: usize,
+}
+
+impl Member {
+    fn new(eth: EthernetAddress, member: bool) -> Self {
+        Self {
+            eth,
+            member,
+            received: 0,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Member {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if self.member {
+            nics.join_group(nics[0].id, GROUP);
+        }
+    }
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        while mail.try_recv().is_some() {
+            self.received += 1;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn multicast_reaches_members_only() {
+    let mut sender = Multicaster {
+        neighbors: vec![(ETH0, ETH3), (ETH1, ETH4), (ETH2, ETH5)],
+    };
+    let mut a = Member::new(ETH3, true);
+    let mut b = Member::new(ETH4, false);
+    let mut c = Member::new(ETH5, true);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, a, b, c];
+    let stats = run_sim(nodes).expect("Sim runs to completion");
+
+    assert_eq!(stats.node(0).sent, 2);
+    assert_eq!((a.received, b.received, c.received), (1, 0, 1));
+}
@@ -0,0 +1,119 @@
+use smoltcp::wire::EthernetAddress;
+
+use crate::message::Mailbox;
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+};
+
+/// Sends a message to its neighbor when the simulation starts.
+pub(super) struct Sender {
+    eth: EthernetAddress,
+    neighbor: EthernetAddress,
+    latency: Option<u64>,
+    count: usize,
+    /// Bytes in each message, "hello" if unset.
+    size: Option<usize>,
+    sent: bool,
+}
+
+impl Sender {
+    pub(super) fn new(eth: EthernetAddress, neighbor: EthernetAddress) -> Self {
+        Self {
+            eth,
+            neighbor,
+            latency: None,
+            count: 1,
+            size: None,
+            sent: false,
+        }
+    }
+
+    /// Send `count` messages instead of one.
+    pub(super) fn count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Send messages of `size` zero bytes instead of "hello".
+    pub(super) fn size(mut self, size: usize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub(super) fn latency(mut self, latency: u64) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Sender {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, self.latency);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.neighbor).unwrap();
+    }
+
+    fn reset(&mut self) {
+        self.sent = false;
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if !self.sent {
+            self.sent = true;
+            for _ in 0..self.count {
+                let data = match self.size {
+                    Some(size) => vec![0; size],
+                    None => b"hello".to_vec(),
+                };
+                mail.send(&nics[0], data)?;
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Counts the messages it receives.
+pub(super) struct Receiver {
+    eths: Vec<EthernetAddress>,
+    pub(super) received: usize,
+}
+
+impl Receiver {
+    pub(super) fn new(eth: EthernetAddress) -> Self {
+        Self {
+            eths: vec![eth],
+            received: 0,
+        }
+    }
+
+    /// Add another, unlinked, nic.
+    pub(super) fn nic(mut self, eth: EthernetAddress) -> Self {
+        self.eths.push(eth);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Receiver {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for eth in &self.eths {
+            nics.nic(*eth, None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        mail.recv().await;
+        self.received += 1;
+        Ok(Continue::KeepRunning)
+    }
+}
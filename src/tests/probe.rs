@@ -0,0 +1,51 @@
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::node::probe::ProbeNode;
+use crate::simulator::{sim_setup, Sim};
+use crate::{node::Node, nodes};
+
+#[test]
+fn inline_probe_captures_what_crosses_it() {
+    let mut left = Sender::new(ETH0, ETH1).latency(10).count(2);
+    let mut probe = ProbeNode::inline(ETH1, ETH2);
+    let mut right = Sender::new(ETH3, ETH2);
+    let nodes: &mut [&mut dyn Node] = nodes![left, probe, right];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    // Each side got what the other sent.
+    assert_eq!(sim.stats().node(0).received, 1);
+    assert_eq!(sim.stats().node(2).received, 2);
+    drop(sim);
+
+    let crossed: Vec<_> = probe
+        .captured()
+        .iter()
+        .map(|frame| (frame.at, frame.from, frame.to, &frame.data[..]))
+        .collect();
+    assert_eq!(
+        crossed,
+        [
+            (0, 3, 2, &b"hello"[..]),
+            (10, 0, 1, b"hello"),
+            (10, 0, 1, b"hello")
+        ]
+    );
+}
+
+#[test]
+fn inline_probe_drops_what_it_cannot_pass_on() {
+    let mut left = Sender::new(ETH0, ETH1);
+    let mut probe = ProbeNode::inline(ETH1, ETH2);
+    let mut right = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![left, probe, right];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    // The probe's second nic can only receive.
+    topology.link_simplex(3, 2).expect("Nics are free");
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(2).received, 0);
+    drop(sim);
+
+    assert_eq!(probe.captured().len(), 1);
+    assert_eq!(probe.dropped(), 1);
+}
@@ -0,0 +1,20 @@
+use std::time::{Duration, Instant};
+
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1};
+use crate::node::Node;
+use crate::nodes;
+use crate::simulator::run_sim_realtime;
+
+#[test]
+fn realtime_run_takes_scaled_wall_time() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(500);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let started = Instant::now();
+    // 500ns of virtual time at 40_000x is 20ms.
+    let stats = run_sim_realtime(nodes, 40_000.0).expect("Sim runs to completion");
+    assert!(started.elapsed() >= Duration::from_millis(20));
+    assert_eq!(stats.link(0).received, 1);
+    assert_eq!(receiver.received, 1);
+}
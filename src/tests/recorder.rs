@@ -0,0 +1,139 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::Sender;
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::message::{Mailbox, MsgId};
+use crate::simulator::recorder::replay;
+use crate::simulator::{run_sim, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Keeps every message it receives, with when it arrived.
+struct Collector {
+    eth: EthernetAddress,
+    log: Vec<(u64, Vec<u8>)>,
+    ids: Vec<MsgId>,
+    activations: usize,
+}
+
+impl Collector {
+    fn new(eth: EthernetAddress) -> Self {
+        Self {
+            eth,
+            log: Vec::new(),
+            ids: Vec::new(),
+            activations: 0,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Collector {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        self.activations += 1;
+        while let Some(msg) = mail.try_recv() {
+            self.ids.push(msg.id());
+            self.log.push((msg.from(), msg.into_data()));
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn replay_reproduces_state() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(25).count(3);
+    let mut collector = Collector::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, collector];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.record();
+    sim.run().expect("Sim runs to completion");
+    let recorder = sim.take_recorder().expect("Sim was recording");
+    drop(sim);
+
+    assert_eq!(recorder.deliveries().len(), 3);
+    assert!(recorder
+        .deliveries()
+        .iter()
+        .all(|delivery| delivery.at == 25));
+
+    let mut replayed = Collector::new(ETH1);
+    replay(
+        &recorder,
+        nodes![Sender::new(ETH0, ETH1).latency(25).count(3), replayed],
+    )
+    .expect("Replay runs to completion");
+    assert_eq!(replayed.log, collector.log);
+    assert_eq!(replayed.ids, collector.ids);
+    assert_eq!(replayed.activations, collector.activations);
+}
+
+/// Forwards everything from its first nic, linked to ETH0, out of its second, linked to ETH3.
+struct Relay;
+
+#[async_trait::async_trait]
+impl Node for Relay {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+        nics.nic(ETH2, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[1].id, &ETH3).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        while let Some(msg) = mail.try_recv() {
+            mail.forward(msg, &nics[1])?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn msg_ids_increase_and_survive_forwarding() {
+    let mut sender = Sender::new(ETH0, ETH1).count(3);
+    let mut second = Sender::new(ETH4, ETH5).count(2);
+    let mut relay = Relay;
+    let mut collector = Collector::new(ETH3);
+    let mut other = Collector::new(ETH5);
+    run_sim(nodes![sender, second, relay, collector, other]).expect("Sim runs to completion");
+
+    assert_eq!(collector.ids, [MsgId(1), MsgId(2), MsgId(3)]);
+    assert_eq!(other.ids, [MsgId(4), MsgId(5)]);
+}
+
+#[test]
+fn chrome_trace_has_a_span_per_delivery() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(2500).count(2);
+    let mut collector = Collector::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, collector];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.record();
+    sim.run().expect("Sim runs to completion");
+    let recorder = sim.take_recorder().expect("Sim was recording");
+
+    let trace: serde_json::Value =
+        serde_json::from_str(&recorder.to_chrome_trace()).expect("Trace is valid JSON");
+    let events = trace["traceEvents"].as_array().expect("Trace has events");
+    assert_eq!(events.len(), recorder.deliveries().len());
+    for event in events {
+        assert_eq!(event["ph"], "X");
+        assert_eq!(event["ts"], 0.0);
+        assert_eq!(event["dur"], 2.5);
+        assert_eq!(event["tid"], 1);
+        assert_eq!(event["args"]["bytes"], 5);
+    }
+}
@@ -0,0 +1,98 @@
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::message::Mailbox;
+use crate::simulator::{topology, Sim, SimErr};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+#[test]
+fn trials_after_reset_are_independent() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10).count(2);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    let link = sim.topology().link_between(0, 1).expect("Nodes are linked");
+    // 8ns a byte, so the second frame waits 40ns behind the first.
+    assert!(sim.topology.set_bandwidth(link, Some(1_000_000_000)));
+    sim.record();
+
+    let mut trials = Vec::new();
+    for _ in 0..2 {
+        sim.run().expect("Sim runs to completion");
+        let recorded = sim.recorder().expect("Sim is recording").deliveries().len();
+        trials.push((sim.now(), sim.stats().link(0).received, recorded));
+        sim.reset().expect("Nodes start up again");
+        assert_eq!(sim.now(), 0);
+        assert_eq!(sim.stats().link(0).received, 0);
+    }
+    assert_eq!(trials, [(90, 2, 2), (90, 2, 2)]);
+    assert_eq!(sim.topology.links.len(), 1);
+    drop(sim);
+    // Receiver keeps its count, since it has no `reset` of its own.
+    assert_eq!(receiver.received, 4);
+}
+
+#[test]
+fn reset_keeps_links_made_outside_startup() {
+    let mut hub = Receiver::new(ETH0).nic(ETH1);
+    let mut left = Receiver::new(ETH2).nic(ETH3);
+    let mut right = Receiver::new(ETH4).nic(ETH5);
+    let nodes: &mut [&mut dyn Node] = nodes![hub, left, right];
+    let mut topology = topology::star(nodes).expect("Star is generated");
+    let simplex = topology.link_simplex(3, 5).expect("Nics are free");
+    assert!(topology.set_bandwidth(simplex, Some(1_000_000)));
+    let mut sim = Sim::new(nodes, topology);
+    let links = sim.topology.links.clone();
+    let nics = sim.topology.all_nics().to_vec();
+    assert_eq!(links.len(), 3);
+
+    sim.run().expect("Sim runs to completion");
+    sim.reset().expect("Nodes start up again");
+    assert_eq!(sim.topology.links, links);
+    assert_eq!(sim.topology.all_nics(), nics);
+    sim.run().expect("Sim runs again");
+}
+
+/// Fails as soon as a message arrives, counting how many times it did.
+struct Brittle {
+    failures: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for Brittle {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        mail.recv().await;
+        self.failures += 1;
+        Err(NodeError::Unreachable)
+    }
+}
+
+#[test]
+fn reset_recovers_a_failed_node() {
+    let mut sender = Sender::new(ETH0, ETH1);
+    let mut brittle = Brittle { failures: 0 };
+    let nodes: &mut [&mut dyn Node] = nodes![sender, brittle];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    for _ in 0..2 {
+        let result = sim.run();
+        assert!(matches!(
+            result,
+            Err(SimErr::NodeFailed {
+                node: 1,
+                error: NodeError::Unreachable
+            })
+        ));
+        sim.reset().expect("Nodes start up again");
+    }
+    drop(sim);
+    assert_eq!(brittle.failures, 2);
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::{ETH0, ETH1, ETH2, ETH3, ETH5};
+use crate::message::Mailbox;
+use crate::node::router::{RoutedPacket, RouterNode};
+use crate::simulator::Sim;
+use crate::{
+    nics::{NicAllocator, NicGroup, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Linked to a router port. Sends a packet to `dst` when the simulation starts, if given one.
+struct Host {
+    mac: EthernetAddress,
+    port: EthernetAddress,
+    dst: Option<NicGroup>,
+    received: Vec<RoutedPacket>,
+}
+
+impl Host {
+    fn new(mac: EthernetAddress, port: EthernetAddress, dst: Option<NicGroup>) -> Self {
+        Self {
+            mac,
+            port,
+            dst,
+            received: Vec::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Host {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.mac, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.port).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if let Some(dst) = self.dst.take() {
+            let packet = RoutedPacket::new(dst, b"hello".to_vec());
+            mail.send(&nics[0], packet.to_bytes())?;
+        }
+        while let Some(msg) = mail.try_recv() {
+            self.received.push(RoutedPacket::parse(msg.data()).unwrap());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn router_forwards_between_hosts() {
+    // Nodes: A = 0, R = 1, B = 2
+    let mut a = Host::new(ETH0, ETH2, Some(2));
+    let mut router = RouterNode::new(vec![ETH2, ETH3], HashMap::from([(0, 0), (2, 1)]));
+    let mut b = Host::new(ETH1, ETH3, None);
+    let nodes: &mut [&mut dyn Node] = nodes![a, router, b];
+    Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .run()
+        .expect("Sim runs to completion");
+
+    assert!(a.received.is_empty());
+    assert_eq!(
+        b.received,
+        vec![RoutedPacket {
+            dst: 2,
+            hops: 1,
+            payload: b"hello".to_vec(),
+        }]
+    );
+}
+
+#[test]
+fn route_over_an_unlinked_port_drops_the_packet() {
+    // Nodes: A = 0, R = 1, B = 2. Group 9 is routed out of a port that was never linked.
+    let mut a = Host::new(ETH0, ETH2, Some(9));
+    let mut router = RouterNode::new(vec![ETH2, ETH3, ETH5], HashMap::from([(2, 1), (9, 2)]));
+    let mut b = Host::new(ETH1, ETH3, None);
+    let nodes: &mut [&mut dyn Node] = nodes![a, router, b];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(1).received, 1);
+    assert_eq!(sim.stats().node(1).sent, 0);
+    drop(sim);
+    assert!(b.received.is_empty());
+}
+
+#[test]
+fn routed_packet_round_trip() {
+    let packet = RoutedPacket::new(7, vec![1, 2, 3]);
+    assert_eq!(RoutedPacket::parse(&packet.to_bytes()), Some(packet));
+    assert_eq!(RoutedPacket::parse(&[0; 8]), None);
+}
+
+/// Forwards everything straight back where it came from, so two of them make a routing loop.
+/// The one with a neighbor starts the loop off.
+struct Bouncer {
+    eth: EthernetAddress,
+    neighbor: Option<EthernetAddress>,
+    ttls: Vec<u8>,
+}
+
+impl Bouncer {
+    fn new(eth: EthernetAddress, neighbor: Option<EthernetAddress>) -> Self {
+        Self {
+            eth,
+            neighbor,
+            ttls: Vec::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Bouncer {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, Some(1));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(neighbor) = self.neighbor {
+            nics.link(nics[0].id, &neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.neighbor.take().is_some() {
+            mail.send(&nics[0], b"around".to_vec())?;
+        }
+        while let Some(msg) = mail.try_recv() {
+            self.ttls.push(msg.ttl());
+            mail.forward(msg, &nics[0])?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn looping_frame_expires() {
+    let mut a = Bouncer::new(ETH0, Some(ETH1));
+    let mut b = Bouncer::new(ETH1, None);
+    let nodes: &mut [&mut dyn Node] = nodes![a, b];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("The loop ends");
+    // Sent with a TTL of 64, the frame is received 64 times before `a` forwards it with none left.
+    assert_eq!(sim.stats().link(0).received, 64);
+    assert_eq!(sim.stats().link(0).expired, 1);
+    assert_eq!(sim.stats().node(0).expired, 1);
+    assert_eq!(sim.stats().node(0).dropped, 1);
+    drop(sim);
+    assert_eq!(b.ttls[..2], [64, 62]);
+    assert_eq!(a.ttls.last(), Some(&1));
+}
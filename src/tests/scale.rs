@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1};
+use crate::message::Mailbox;
+use crate::simulator::{run_sim_boxed, topology, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes_vec,
+};
+
+/// Broadcasts `payload` when the simulation starts if it has one, and keeps what it receives.
+struct Fanout {
+    nics: usize,
+    payload: Option<Arc<[u8]>>,
+    received: Option<Arc<[u8]>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Fanout {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        for _ in 0..self.nics {
+            nics.nic_auto(None);
+        }
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if let Some(payload) = &self.payload {
+            mail.broadcast(Arc::clone(payload), nics);
+        } else if let Some(msg) = mail.try_recv() {
+            self.received = Some(msg.into_shared());
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn broadcast_shares_payload() {
+    const RECEIVERS: usize = 100;
+    let payload: Arc<[u8]> = vec![7; 1400].into();
+    let mut fanouts: Vec<Fanout> = (0..=RECEIVERS)
+        .map(|node| Fanout {
+            nics: if node == 0 { RECEIVERS } else { 1 },
+            payload: (node == 0).then(|| Arc::clone(&payload)),
+            received: None,
+        })
+        .collect();
+    let mut nodes: Vec<&mut dyn Node> = fanouts
+        .iter_mut()
+        .map(|fanout| fanout as &mut dyn Node)
+        .collect();
+    let topology = topology::star(&mut nodes).expect("Star is generated");
+    Sim::new(&mut nodes, topology)
+        .run()
+        .expect("Sim runs to completion");
+    drop(nodes);
+
+    for fanout in &fanouts[1..] {
+        let received = fanout.received.as_ref().expect("Broadcast is received");
+        assert!(Arc::ptr_eq(received, &payload));
+    }
+}
+
+#[test]
+fn boxed_nodes_built_in_a_loop() {
+    let mut nodes = nodes_vec![Sender::new(ETH0, ETH1), Receiver::new(ETH1)];
+    for pair in 1..50 {
+        let sender = EthernetAddress([2, 0, 0, 1, pair, 0]);
+        let receiver = EthernetAddress([2, 0, 0, 1, pair, 1]);
+        nodes.push(Box::new(Sender::new(sender, receiver)));
+        nodes.push(Box::new(Receiver::new(receiver)));
+    }
+    assert_eq!(nodes.len(), 100);
+
+    let stats = run_sim_boxed(&mut nodes).expect("Sim runs to completion");
+    assert!((0..100).step_by(2).all(|node| stats.node(node).sent == 1));
+    assert!((1..100)
+        .step_by(2)
+        .all(|node| stats.node(node).received == 1));
+}
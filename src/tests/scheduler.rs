@@ -0,0 +1,146 @@
+use std::sync::{Arc, Mutex};
+
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::Sender;
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::simulator::{SchedulerPolicy, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Notes down its index and the time whenever `process` is called, sending once to its neighbor if it has one.
+struct Logger {
+    index: usize,
+    eth: EthernetAddress,
+    neighbor: Option<EthernetAddress>,
+    sent: bool,
+    log: Arc<Mutex<Vec<(usize, u64)>>>,
+}
+
+impl Logger {
+    fn new(index: usize, eth: EthernetAddress, log: &Arc<Mutex<Vec<(usize, u64)>>>) -> Self {
+        Self {
+            index,
+            eth,
+            neighbor: None,
+            sent: false,
+            log: Arc::clone(log),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Logger {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, Some(10));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(neighbor) = self.neighbor {
+            nics.link(nics[0].id, &neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        self.log.lock().unwrap().push((self.index, mail.now()));
+        while mail.try_recv().is_some() {}
+        if self.neighbor.is_some() && !self.sent {
+            self.sent = true;
+            mail.send(&nics[0], b"tick".to_vec())?;
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Node 0 sends node 2 one message, arriving 20ns in, while node 1 sits idle.
+fn activations(policy: SchedulerPolicy) -> Vec<(usize, u64)> {
+    let log = Arc::default();
+    let mut sender = Logger::new(0, ETH0, &log);
+    sender.neighbor = Some(ETH2);
+    let mut bystander = Logger::new(1, ETH1, &log);
+    let mut receiver = Logger::new(2, ETH2, &log);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, bystander, receiver];
+    let mut sim = Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .with_scheduler(policy);
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    let log = log.lock().unwrap().clone();
+    log
+}
+
+#[test]
+fn round_robin_polls_every_node_each_tick() {
+    // Everyone starts at 0, then only the receiver has a reason to run.
+    assert_eq!(
+        activations(SchedulerPolicy::EventDriven),
+        [(0, 0), (1, 0), (2, 0), (2, 20)]
+    );
+    // Every tick, every node, in index order.
+    assert_eq!(
+        activations(SchedulerPolicy::RoundRobin),
+        [(0, 0), (1, 0), (2, 0), (0, 20), (1, 20), (2, 20)]
+    );
+}
+
+/// Notes down its index for every message it takes, taking as many as it can.
+struct Greedy {
+    index: usize,
+    eth: EthernetAddress,
+    log: Arc<Mutex<Vec<usize>>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Greedy {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        while mail.try_recv().is_some() {
+            self.log.lock().unwrap().push(self.index);
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+/// Node 0 gets four messages and node 1 one, all at the same instant.
+fn takes(budget: Option<usize>) -> Vec<usize> {
+    let log = Arc::default();
+    let mut busy = Greedy {
+        index: 0,
+        eth: ETH0,
+        log: Arc::clone(&log),
+    };
+    let mut quiet = Greedy {
+        index: 1,
+        eth: ETH1,
+        log: Arc::clone(&log),
+    };
+    let mut flood = Sender::new(ETH2, ETH0).count(4);
+    let mut single = Sender::new(ETH3, ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![busy, quiet, flood, single];
+    let mut sim = Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .with_process_budget(budget);
+    sim.run().expect("Sim runs to completion");
+    drop(sim);
+    let log = log.lock().unwrap().clone();
+    log
+}
+
+#[test]
+fn process_budget_gives_other_nodes_a_turn() {
+    assert_eq!(takes(None), [0, 0, 0, 0, 1]);
+    assert_eq!(takes(Some(2)), [0, 0, 1, 0, 0]);
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use smoltcp::wire::EthernetAddress;
+
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::sequence::SequencedChannel;
+use crate::message::Mailbox;
+use crate::simulator::{sim_setup, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Sends frames 1, 2 and 3 through a `SequencedChannel` when the simulation starts,
+/// or, with no neighbor, keeps what the channel yields.
+struct Peer {
+    eth: EthernetAddress,
+    neighbor: Option<EthernetAddress>,
+    channel: SequencedChannel,
+    sent: bool,
+    received: Vec<u8>,
+}
+
+impl Peer {
+    fn new(eth: EthernetAddress, neighbor: Option<EthernetAddress>) -> Self {
+        Self {
+            eth,
+            neighbor,
+            channel: SequencedChannel::new(Duration::from_micros(1)),
+            sent: false,
+            received: Vec::new(),
+        }
+    }
+
+    /// Receive with `SequencedChannel::dedup_on_receive`, and keep whatever arrives.
+    fn dedup(mut self) -> Self {
+        self.channel = SequencedChannel::new(Duration::from_micros(1)).dedup_on_receive(Some(16));
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Peer {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, Some(5));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(neighbor) = self.neighbor {
+            nics.link(nics[0].id, &neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if self.neighbor.is_some() {
+            if !self.sent {
+                self.sent = true;
+                for frame in 1..=3 {
+                    self.channel.send(mail, &nics[0], vec![frame])?;
+                }
+            }
+        } else {
+            loop {
+                let msg = self.channel.recv(mail).await;
+                self.received.extend(msg.data());
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn jittered_frames_are_put_in_order() {
+    let mut sender = Peer::new(ETH0, Some(ETH1));
+    let mut receiver = Peer::new(ETH1, None);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_jitter(link, 100));
+    let mut sim = Sim::new(nodes, topology);
+    sim.record();
+    sim.run().expect("Sim runs to completion");
+
+    let recorder = sim.take_recorder().expect("Sim is recording");
+    let arrived: Vec<u8> = recorder
+        .deliveries()
+        .iter()
+        .map(|delivery| *delivery.data.last().unwrap())
+        .collect();
+    assert_ne!(arrived, [1, 2, 3], "jitter reorders the frames");
+    drop(sim);
+    assert_eq!(receiver.received, [1, 2, 3]);
+}
+
+/// Sends frames numbered 0 to 2 the way `SequencedChannel` does, each of them twice.
+struct Stutter;
+
+#[async_trait::async_trait]
+impl Node for Stutter {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH2, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &ETH3).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        for seq in 0..3u32 {
+            let mut frame = seq.to_be_bytes().to_vec();
+            frame.push(seq as u8 + 1);
+            mail.send(&nics[0], frame.clone())?;
+            mail.send(&nics[0], frame)?;
+        }
+        Ok(Continue::Done)
+    }
+}
+
+#[test]
+fn duplicated_frames_are_seen_once() {
+    let mut receiver = Peer::new(ETH3, None).dedup();
+    let nodes: &mut [&mut dyn Node] = nodes![Stutter, receiver];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(1).received, 6);
+    drop(sim);
+    assert_eq!(receiver.received, [1, 2, 3]);
+}
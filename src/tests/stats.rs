@@ -0,0 +1,161 @@
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::simulator::{event::DropReason, run_sim, sim_setup, Sim};
+use crate::{node::Node, nodes};
+
+#[test]
+fn link_counts_messages() {
+    let mut sender = Sender::new(ETH0, ETH1).count(10);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let stats = run_sim(nodes).expect("Sim runs to completion");
+
+    let link = stats.link(0);
+    assert_eq!(link.sent, 10);
+    assert_eq!(link.received, 10);
+    assert_eq!(link.bytes_sent, 50);
+    assert_eq!(link.bytes_received, 50);
+    assert_eq!(link.dropped, 0);
+    assert_eq!(stats.node(0).sent, 10);
+    assert_eq!(stats.node(1).received, 10);
+    assert_eq!(stats.node(1).sent, 0);
+}
+
+#[test]
+fn full_queue_drops_tail() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(100).count(10);
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_max_queue(link, Some(4)));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+
+    let link = sim.stats().link(0);
+    assert_eq!(link.sent, 4);
+    assert_eq!(link.received, 4);
+    assert_eq!(link.dropped, 6);
+}
+
+#[test]
+fn report_totals_the_run() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(100).count(10);
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_max_queue(link, Some(4)));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+
+    let report = sim.report();
+    assert_eq!(report.now, 100);
+    assert_eq!((report.sent, report.received), (4, 4));
+    assert_eq!((report.bytes_sent, report.bytes_received), (20, 20));
+    assert_eq!(report.drops, [(DropReason::QueueFull, 6)]);
+    assert_eq!(report.nodes.len(), 2);
+    assert_eq!(report.nodes[0].1.dropped, 6);
+    assert_eq!(report.nodes[1].1.received, 4);
+    let table = report.to_string();
+    assert!(table.contains("4 sent, 4 received, 6 dropped"));
+    assert!(table.contains("QueueFull"));
+}
+
+/// The bytes delivered over a link that corrupts half its bytes, with the given seed.
+fn corrupted_run(seed: u64) -> (Vec<Vec<u8>>, u64) {
+    let mut sender = Sender::new(ETH0, ETH1).count(4);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_bit_error_rate(link, 0.5));
+    let mut sim = Sim::new(nodes, topology);
+    sim.seed(seed);
+    sim.record();
+    sim.run().expect("Sim runs to completion");
+
+    let corrupted = sim.stats().link(0).corrupted;
+    let recorder = sim.take_recorder().expect("Sim is recording");
+    let data = recorder
+        .deliveries()
+        .iter()
+        .map(|delivery| delivery.data.clone())
+        .collect();
+    (data, corrupted)
+}
+
+#[test]
+fn bit_errors_are_deterministic() {
+    let (data, corrupted) = corrupted_run(7);
+    assert_eq!(data.len(), 4);
+    assert!(data.iter().all(|data| data.len() == 5));
+    assert!(data.iter().any(|data| data != b"hello"));
+    assert_eq!(
+        corrupted,
+        data.iter().filter(|data| *data != b"hello").count() as u64
+    );
+    assert_eq!(corrupted_run(7), (data.clone(), corrupted));
+    assert_ne!(corrupted_run(8).0, data);
+}
+
+#[test]
+fn duplicated_frames_arrive_twice() {
+    let mut sender = Sender::new(ETH2, ETH3).latency(10).count(3);
+    let mut receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_duplicate_probability(link, 1.0));
+    let mut sim = Sim::new(nodes, topology);
+    sim.record();
+    sim.run().expect("Sim runs to completion");
+
+    let link = sim.stats().link(0);
+    assert_eq!(link.sent, 3);
+    assert_eq!(link.duplicated, 3);
+    assert_eq!(link.received, 6);
+    assert_eq!(sim.stats().node(0).duplicated, 3);
+    let recorder = sim.take_recorder().expect("Sim is recording");
+    let times: Vec<u64> = recorder.deliveries().iter().map(|d| d.at).collect();
+    assert_eq!(times, [10, 10, 10, 11, 11, 11]);
+}
+
+#[test]
+fn latency_histogram_tail_is_the_slow_path() {
+    let mut fast = Sender::new(ETH0, ETH1).latency(10).count(90);
+    let mut fast_receiver = Receiver::new(ETH1);
+    let mut slow = Sender::new(ETH2, ETH3).latency(1000).count(10);
+    let mut slow_receiver = Receiver::new(ETH3);
+    let nodes: &mut [&mut dyn Node] = nodes![fast, fast_receiver, slow, slow_receiver];
+    let stats = run_sim(nodes).expect("Sim runs to completion");
+
+    let latencies = stats.latencies();
+    assert_eq!(latencies.count(), 100);
+    assert_eq!(latencies.iter().collect::<Vec<_>>(), [(10, 90), (1000, 10)]);
+    assert_eq!(latencies.p50(), Some(10));
+    assert_eq!(latencies.p99(), Some(1000));
+    assert_eq!(latencies.max(), Some(1000));
+}
+
+/// The latencies of four frames sent at once over a one gigabit link, fair queued with `weights`.
+fn queued_latencies(weights: Option<Vec<(u32, u32)>>) -> Vec<(u64, u64)> {
+    let mut sender = Sender::new(ETH0, ETH1).count(4);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_bandwidth(link, Some(1_000_000_000)));
+    assert!(topology.set_flow_weights(link, weights));
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    sim.stats().latencies().iter().collect()
+}
+
+#[test]
+fn latency_includes_time_on_a_fair_queue() {
+    // Each frame takes 40ns to send, behind the ones sent before it.
+    let expected = [(40, 1), (80, 1), (120, 1), (160, 1)];
+    assert_eq!(queued_latencies(None), expected);
+    assert_eq!(queued_latencies(Some(Vec::new())), expected);
+}
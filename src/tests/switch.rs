@@ -0,0 +1,178 @@
+use smoltcp::wire::{EthernetAddress, EthernetFrame, EthernetProtocol};
+
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::message::Mailbox;
+use crate::node::switch::{SwitchNode, VlanPort};
+use crate::simulator::{sim_setup, LinkDuplex, Sim};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+fn frame(src: EthernetAddress, dst: EthernetAddress) -> Vec<u8> {
+    let mut buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(4)];
+    let mut frame = EthernetFrame::new_unchecked(&mut buffer);
+    frame.set_src_addr(src);
+    frame.set_dst_addr(dst);
+    frame.set_ethertype(EthernetProtocol::Unknown(0x88b5));
+    frame.payload_mut().copy_from_slice(b"ping");
+    buffer
+}
+
+/// Plugged into a switch port. Replies to frames addressed to it, until it runs out of replies.
+struct Host {
+    mac: EthernetAddress,
+    port: EthernetAddress,
+    /// Sends a frame to this MAC when the simulation starts.
+    initiate: Option<EthernetAddress>,
+    replies: usize,
+    /// Every frame that reaches the host, addressed to it or not.
+    seen: usize,
+}
+
+impl Host {
+    fn new(mac: EthernetAddress, port: EthernetAddress, replies: usize) -> Self {
+        Self {
+            mac,
+            port,
+            initiate: None,
+            replies,
+            seen: 0,
+        }
+    }
+
+    fn initiate(mut self, dst: EthernetAddress) -> Self {
+        self.initiate = Some(dst);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Host {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.mac, None);
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        nics.link(nics[0].id, &self.port).unwrap();
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if let Some(dst) = self.initiate.take() {
+            mail.send(&nics[0], frame(self.mac, dst))?;
+        }
+        while let Some(msg) = mail.try_recv() {
+            self.seen += 1;
+            let received = EthernetFrame::new_checked(msg.data()).unwrap();
+            if received.dst_addr() == self.mac && self.replies > 0 {
+                self.replies -= 1;
+                mail.send(&nics[0], frame(self.mac, received.src_addr()))?;
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn switch_learns_and_stops_flooding() {
+    let mut switch = SwitchNode::new(vec![ETH0, ETH1, ETH2]);
+    // A pings B, B replies, then A pings B again.
+    let mut a = Host::new(ETH3, ETH0, 1).initiate(ETH4);
+    let mut b = Host::new(ETH4, ETH1, 1);
+    let mut c = Host::new(ETH5, ETH2, 0);
+    let nodes: &mut [&mut dyn Node] = nodes![switch, a, b, c];
+    Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .run()
+        .expect("Sim runs to completion");
+
+    assert_eq!(a.seen, 1);
+    assert_eq!(b.seen, 2);
+    // Only the first frame, before B's port was known, was flooded.
+    assert_eq!(c.seen, 1);
+    assert_eq!(switch.port_of(&ETH3), Some(0));
+    assert_eq!(switch.port_of(&ETH4), Some(1));
+    assert_eq!(switch.port_of(&ETH5), None);
+}
+
+#[test]
+fn flooding_skips_a_receive_only_port() {
+    let mut switch = SwitchNode::new(vec![ETH0, ETH1, ETH2]);
+    let mut a = Host::new(ETH3, ETH0, 0).initiate(ETH4);
+    let mut b = Host::new(ETH4, ETH1, 0);
+    let mut c = Host::new(ETH5, ETH2, 0);
+    let nodes: &mut [&mut dyn Node] = nodes![switch, a, b, c];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(5, 2).expect("Nodes are linked");
+    // C can send to the switch, but the switch can't send back.
+    assert!(topology.set_duplex(link, LinkDuplex::Simplex));
+    Sim::new(nodes, topology)
+        .run()
+        .expect("Sim runs to completion");
+
+    assert_eq!(b.seen, 1);
+    assert_eq!(c.seen, 0);
+}
+
+#[test]
+fn vlans_are_isolated() {
+    let host = |n| EthernetAddress([2, 0, 0, 0, 0, n]);
+    let mut switch = SwitchNode::with_vlans(vec![
+        (ETH0, VlanPort::Access(10)),
+        (ETH1, VlanPort::Access(10)),
+        (ETH2, VlanPort::Access(20)),
+        (
+            ETH3,
+            VlanPort::Trunk {
+                vlans: vec![10, 20],
+                native: None,
+            },
+        ),
+    ]);
+    // A's frame to C is flooded, but C is on another VLAN.
+    let mut a = Host::new(host(1), ETH0, 0).initiate(host(3));
+    let mut b = Host::new(host(2), ETH1, 0);
+    let mut c = Host::new(host(3), ETH2, 0);
+    let mut trunk = Host::new(host(4), ETH3, 0);
+    let nodes: &mut [&mut dyn Node] = nodes![switch, a, b, c, trunk];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    // Out of B's access port, and tagged out of the trunk.
+    assert_eq!(sim.stats().node(0).sent, 2);
+    assert_eq!(sim.stats().node(4).bytes_received, 22);
+    drop(sim);
+
+    assert_eq!(b.seen, 1);
+    assert_eq!(c.seen, 0);
+    assert_eq!(trunk.seen, 1);
+    assert_eq!(switch.vlan_port_of(10, &host(1)), Some(0));
+    assert_eq!(switch.vlan_port_of(20, &host(1)), None);
+}
+
+#[test]
+fn trunk_drops_untagged_without_native_vlan() {
+    let mut switch = SwitchNode::with_vlans(vec![
+        (ETH0, VlanPort::Access(10)),
+        (
+            ETH1,
+            VlanPort::Trunk {
+                vlans: vec![10],
+                native: None,
+            },
+        ),
+    ]);
+    let mut a = Host::new(ETH2, ETH0, 0);
+    // The trunk's frame is untagged, so belongs to no VLAN.
+    let mut trunk = Host::new(ETH3, ETH1, 0).initiate(ETH2);
+    let nodes: &mut [&mut dyn Node] = nodes![switch, a, trunk];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.stats().node(0).sent, 0);
+    drop(sim);
+    assert_eq!(a.seen, 0);
+}
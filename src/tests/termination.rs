@@ -0,0 +1,187 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::Sender;
+use super::{ETH0, ETH1, ETH2, ETH3};
+use crate::message::Mailbox;
+use crate::simulator::{run_sim_until, Sim, SimErr};
+use crate::{
+    nics::{NicAllocator, Nics, NicsMut},
+    node::{Continue, Node, NodeError},
+    nodes,
+};
+
+/// Bounces every message straight back, forever.
+struct PingPong {
+    eth: EthernetAddress,
+    /// Links to the neighbor and sends the first message when set.
+    neighbor: Option<EthernetAddress>,
+    latency: u64,
+    started: bool,
+}
+
+impl PingPong {
+    fn new(eth: EthernetAddress, neighbor: Option<EthernetAddress>) -> Self {
+        Self {
+            eth,
+            neighbor,
+            latency: 5,
+            started: false,
+        }
+    }
+
+    fn latency(mut self, latency: u64) -> Self {
+        self.latency = latency;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for PingPong {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(self.eth, Some(self.latency));
+    }
+
+    fn startup(&mut self, nics: &mut NicsMut<'_>) {
+        if let Some(neighbor) = self.neighbor {
+            nics.link(nics[0].id, &neighbor).unwrap();
+        }
+    }
+
+    async fn process(
+        &mut self,
+        mail: &mut Mailbox,
+        nics: &Nics<'_>,
+    ) -> Result<Continue, NodeError> {
+        if !self.started {
+            self.started = true;
+            if self.neighbor.is_some() {
+                mail.send(&nics[0], b"ping".to_vec())?;
+            }
+            return Ok(Continue::KeepRunning);
+        }
+        let msg = mail.recv().await;
+        mail.send(&nics[0], msg.into_data())?;
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn run_until_stops_at_max_time() {
+    let mut ping = PingPong::new(ETH0, Some(ETH1));
+    let mut pong = PingPong::new(ETH1, None);
+    let nodes: &mut [&mut dyn Node] = nodes![ping, pong];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run_until(1000).expect("Sim runs to its limit");
+    assert_eq!(sim.now(), 1000);
+    // Each bounce takes 10ns.
+    assert_eq!(sim.stats().link(0).received, 100);
+}
+
+#[test]
+fn run_sim_until_returns() {
+    let mut ping = PingPong::new(ETH2, Some(ETH3));
+    let mut pong = PingPong::new(ETH3, None);
+    let stats = run_sim_until(nodes![ping, pong], 1005).expect("Sim runs to its limit");
+    assert_eq!(stats.link(0).sent, 101);
+    assert_eq!(stats.link(0).received, 100);
+}
+
+#[test]
+fn zero_latency_ping_pong_stalls() {
+    let mut ping = PingPong::new(ETH0, Some(ETH1)).latency(0);
+    let mut pong = PingPong::new(ETH1, None).latency(0);
+    let nodes: &mut [&mut dyn Node] = nodes![ping, pong];
+    let mut sim = Sim::build(nodes)
+        .expect("Sim correctly initializes")
+        .with_instant_limit(100);
+    // Time never passes, so `max_time` alone would not stop it.
+    let err = sim
+        .run_until(1000)
+        .expect_err("The clock never leaves the first instant");
+    assert!(matches!(err, SimErr::Stalled { at: 0 }));
+    // The bounce past the limit was handled before the run failed.
+    assert_eq!(sim.stats().link(0).received, 101);
+}
+
+/// Finishes after its first message.
+#[derive(Default)]
+struct OneShot {
+    polls: usize,
+    received: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for OneShot {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        self.polls += 1;
+        mail.recv().await;
+        self.received += 1;
+        Ok(Continue::Done)
+    }
+}
+
+#[test]
+fn done_node_is_not_polled_again() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10).count(3);
+    let mut one_shot = OneShot::default();
+    let nodes: &mut [&mut dyn Node] = nodes![sender, one_shot];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    sim.run().expect("Sim runs to completion");
+    // Every message arrives, but only the first is read.
+    assert_eq!(sim.stats().node(1).received, 3);
+    drop(sim);
+    assert_eq!(one_shot.polls, 1);
+    assert_eq!(one_shot.received, 1);
+}
+
+/// Panics on its second message.
+#[derive(Default)]
+struct Fragile {
+    received: usize,
+}
+
+#[async_trait::async_trait]
+impl Node for Fragile {
+    fn hardware(&self, nics: &mut NicAllocator) {
+        nics.nic(ETH1, None);
+    }
+
+    fn startup(&mut self, _: &mut NicsMut<'_>) {}
+
+    async fn process(&mut self, mail: &mut Mailbox, _: &Nics<'_>) -> Result<Continue, NodeError> {
+        while mail.try_recv().is_some() {
+            self.received += 1;
+            if self.received == 2 {
+                panic!("fragile node broke on message {}", self.received);
+            }
+        }
+        Ok(Continue::KeepRunning)
+    }
+}
+
+#[test]
+fn node_panic_is_reported() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10).count(3);
+    let mut fragile = Fragile::default();
+    let mut pong = PingPong::new(ETH2, Some(ETH3));
+    let mut ping = PingPong::new(ETH3, None);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, fragile, pong, ping];
+    let mut sim = Sim::build(nodes).expect("Sim correctly initializes");
+    let err = sim.run().expect_err("Fragile node panics");
+    let SimErr::NodePanicked { node, message } = err else {
+        panic!("Expected a panic to be reported, got {err:?}");
+    };
+    assert_eq!(node, 1);
+    assert_eq!(message, "fragile node broke on message 2");
+
+    // The rest of the simulation carries on.
+    sim.run_until(1000).expect("Sim runs to its limit");
+    assert_eq!(sim.stats().node(1).received, 3);
+    assert!(sim.stats().link(1).received > 90);
+}
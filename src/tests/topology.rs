@@ -0,0 +1,343 @@
+use smoltcp::wire::EthernetAddress;
+
+use super::nodes::{Receiver, Sender};
+use super::{ETH0, ETH1, ETH2, ETH3, ETH4, ETH5};
+use crate::node::Node;
+use crate::nodes;
+use crate::simulator::topology::{self, TopologyBuilder};
+use crate::simulator::{sim_setup, Sim, SimErr, Topology};
+
+/// `count` receivers with `nics` unlinked nics each, all with distinct MACs.
+fn receivers(count: u8, nics: u8) -> Vec<Receiver> {
+    (0..count)
+        .map(|node| {
+            (1..nics).fold(
+                Receiver::new(EthernetAddress([2, 0, 0, 0, node, 0])),
+                |receiver, nic| receiver.nic(EthernetAddress([2, 0, 0, 0, node, nic])),
+            )
+        })
+        .collect()
+}
+
+fn as_nodes(receivers: &mut [Receiver]) -> Vec<&mut dyn Node> {
+    receivers
+        .iter_mut()
+        .map(|receiver| receiver as &mut dyn Node)
+        .collect()
+}
+
+#[test]
+fn topology_to_dot() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(250);
+    let mut receiver = Receiver::new(ETH1);
+    let topology = sim_setup(nodes![sender, receiver]).expect("Sim correctly initializes");
+
+    let dot = topology.to_dot();
+    assert!(dot.starts_with("graph topology {"));
+    assert!(dot.contains(&format!("0 [label=\"{}\"];", sender.name())));
+    assert!(dot.contains(&format!("1 [label=\"{}\"];", receiver.name())));
+    assert!(dot.contains("0 -- 1 [label=\"250ns\"];"));
+}
+
+#[test]
+fn dot_shows_the_slower_direction_of_an_override() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(250);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let mut topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let link = topology.link_between(0, 1).expect("Nodes are linked");
+    assert!(topology.set_asymmetric_latency(link, Some((100, 400))));
+    assert!(topology.to_dot().contains("0 -- 1 [label=\"400ns\"];"));
+}
+
+#[test]
+fn topology_counts() {
+    let topology = sim_setup(nodes![
+        Sender::new(ETH0, ETH1),
+        Receiver::new(ETH1).nic(ETH2),
+        Receiver::new(ETH4),
+        Receiver::new(EthernetAddress([2, 0, 0, 0, 0, 0])).nic(EthernetAddress([2, 0, 0, 0, 0, 1]))
+    ])
+    .expect("Sim correctly initializes");
+    assert_eq!(topology.node_count(), 4);
+    assert_eq!(topology.nic_count(), 6);
+}
+
+#[test]
+fn node_slices_follow_groups() {
+    let topology = sim_setup(nodes![
+        Receiver::new(ETH0).nic(ETH1).nic(ETH2),
+        Receiver::new(ETH3),
+        Receiver::new(ETH4).nic(ETH5)
+    ])
+    .expect("Sim correctly initializes");
+    let slices: Vec<Vec<_>> = topology
+        .node_slices()
+        .map(|nics| nics.iter().map(|nic| (nic.group, nic.id)).collect())
+        .collect();
+    assert_eq!(
+        slices,
+        [
+            vec![(0, 0), (0, 1), (0, 2)],
+            vec![(1, 3)],
+            vec![(2, 4), (2, 5)]
+        ]
+    );
+}
+
+#[test]
+fn topology_json_round_trip() {
+    let topology = sim_setup(nodes![
+        Sender::new(ETH0, ETH1).latency(100),
+        Receiver::new(ETH1).nic(ETH2),
+        Sender::new(ETH3, ETH2),
+        Receiver::new(ETH4)
+    ])
+    .expect("Sim correctly initializes");
+
+    let json = topology.to_json();
+    let restored = Topology::from_json(&json).expect("Topology deserializes");
+    assert_eq!(restored.all_nics(), topology.all_nics());
+    assert_eq!(restored.links, topology.links);
+    assert_eq!(restored.to_dot(), topology.to_dot());
+    assert_eq!(restored.to_json(), json);
+}
+
+#[test]
+fn generated_topologies() {
+    let mut nodes = receivers(6, 2);
+    let ring = topology::ring(&mut as_nodes(&mut nodes)).expect("Ring is generated");
+    assert_eq!(ring.links.len(), 6);
+    assert!(ring.all_nics().iter().all(|nic| nic.link_id.is_some()));
+
+    let mut nodes = receivers(5, 4);
+    let star = topology::star(&mut as_nodes(&mut nodes)).expect("Star is generated");
+    assert_eq!(star.links.len(), 4);
+    assert!(star.nics(0).iter().all(|nic| nic.link_id.is_some()));
+
+    let mut nodes = receivers(12, 4);
+    let grid = topology::grid(&mut as_nodes(&mut nodes), 3, 4).expect("Grid is generated");
+    // 3 rows of 3 horizontal links, 2 rows of 4 vertical links
+    assert_eq!(grid.links.len(), 17);
+
+    let mut nodes = receivers(6, 5);
+    let mesh = topology::full_mesh(&mut as_nodes(&mut nodes)).expect("Mesh is generated");
+    assert_eq!(mesh.links.len(), 6 * 5 / 2);
+}
+
+#[test]
+fn generated_topology_without_enough_nics() {
+    let mut nodes = receivers(4, 2);
+    let err = topology::full_mesh(&mut as_nodes(&mut nodes)).expect_err("Nodes only have two nics");
+    assert!(matches!(err, SimErr::NoFreeNic { .. }));
+}
+
+#[test]
+fn topology_builder_chain() {
+    let mut sim = TopologyBuilder::new()
+        .node("A")
+        .nic(ETH0)
+        .node("B")
+        .nic(ETH1)
+        .nic(ETH2)
+        .node("C")
+        .nic(ETH3)
+        .connect("A", "B")
+        .connect("B", "C")
+        .build()
+        .expect("Builder produces a sim");
+
+    let topology = sim.topology();
+    assert_eq!(topology.links.len(), 2);
+    assert_eq!(topology.links[0], (0u64, 1u64));
+    assert_eq!(topology.links[1], (2u64, 3u64));
+    assert!(topology.to_dot().contains("1 [label=\"B\"];"));
+    sim.run().expect("Sim runs to completion");
+
+    let Err(err) = TopologyBuilder::new()
+        .node("A")
+        .nic(ETH0)
+        .connect("A", "Z")
+        .build()
+    else {
+        panic!("Node Z was never declared");
+    };
+    assert!(matches!(err, SimErr::UnknownNode { name } if name == "Z"));
+}
+
+#[test]
+fn links_of_a_chain() {
+    let mut head = Sender::new(ETH0, ETH1);
+    let mut middle = Receiver::new(ETH1).nic(ETH2);
+    let mut tail = Sender::new(ETH3, ETH2);
+    let topology = sim_setup(nodes![head, middle, tail]).expect("Sim correctly initializes");
+
+    let links: Vec<_> = topology.links().collect();
+    assert_eq!(links, [(0, 1), (3, 2)]);
+}
+
+/// A diamond of nodes A, B, C and D, where the path through B is faster than the one through C.
+fn diamond() -> Sim<'static> {
+    let mut sim = TopologyBuilder::new()
+        .node("A")
+        .nic(ETH0)
+        .nic(ETH1)
+        .node("B")
+        .nic(ETH2)
+        .nic(ETH3)
+        .node("C")
+        .nic(ETH4)
+        .nic(ETH5)
+        .node("D")
+        .nic(EthernetAddress([2, 0, 0, 0, 0, 6]))
+        .nic(EthernetAddress([2, 0, 0, 0, 0, 7]))
+        .connect("A", "B")
+        .connect("A", "C")
+        .connect("B", "D")
+        .connect("C", "D")
+        .build()
+        .expect("Builder produces a sim");
+    for (nic, latency) in [
+        (0, 5),
+        (2, 5),
+        (3, 5),
+        (6, 5),
+        (1, 25),
+        (4, 25),
+        (5, 25),
+        (7, 25),
+    ] {
+        sim.topology.nic_mut(nic).latency = Some(latency);
+    }
+    sim
+}
+
+#[test]
+fn shortest_path_prefers_lower_latency() {
+    let sim = diamond();
+    let topology = sim.topology();
+    assert_eq!(topology.shortest_path(0, 3), Some(vec![0, 1, 3]));
+    assert_eq!(topology.shortest_path(3, 0), Some(vec![3, 1, 0]));
+    assert_eq!(topology.shortest_path(2, 2), Some(vec![2]));
+    assert_eq!(topology.path_latency(&[0, 1, 3]), Some(20));
+    assert_eq!(topology.path_latency(&[0, 2, 3]), Some(100));
+    assert_eq!(topology.path_latency(&[0, 3]), None);
+    assert_eq!(topology.shortest_path(0, 4), None);
+}
+
+#[test]
+fn cost_outweighs_latency_when_routing_by_cost() {
+    let mut sim = diamond();
+    // A's fast nic towards B becomes the expensive one.
+    assert!(sim.topology.set_cost(0, 10));
+    assert!(!sim.topology.set_cost(99, 10));
+    let topology = sim.topology();
+    assert_eq!(topology.nic(0).cost(), 10);
+    assert_eq!(topology.nic(1).cost(), 1);
+    assert_eq!(topology.shortest_path(0, 3), Some(vec![0, 1, 3]));
+    assert_eq!(topology.shortest_path_by_cost(0, 3), Some(vec![0, 2, 3]));
+    assert_eq!(topology.path_latency(&[0, 2, 3]), Some(100));
+}
+
+#[test]
+fn spanning_tree_blocks_a_triangle_link() {
+    let mut sim = TopologyBuilder::new()
+        .node("A")
+        .nic(ETH0)
+        .nic(ETH1)
+        .node("B")
+        .nic(ETH2)
+        .nic(ETH3)
+        .node("C")
+        .nic(ETH4)
+        .nic(ETH5)
+        .connect("A", "B")
+        .connect("B", "C")
+        .connect("C", "A")
+        .build()
+        .expect("Builder produces a sim");
+    // Make B to C the slowest link.
+    sim.topology.nic_mut(3).latency = Some(50);
+
+    let topology = sim.topology();
+    assert_eq!(topology.spanning_tree(), [(0, 2), (5, 1)]);
+    assert_eq!(topology.blocked_links(), [(3, 4)]);
+}
+
+#[test]
+fn spanning_tree_weighs_latency_overrides() {
+    let mut sim = TopologyBuilder::new()
+        .node("A")
+        .nic(ETH0)
+        .nic(ETH1)
+        .node("B")
+        .nic(ETH2)
+        .nic(ETH3)
+        .node("C")
+        .nic(ETH4)
+        .nic(ETH5)
+        .connect("A", "B")
+        .connect("B", "C")
+        .connect("C", "A")
+        .build()
+        .expect("Builder produces a sim");
+    // B to C is slow one way only, but still the slowest link.
+    let link = sim.topology.link_between(3, 4).expect("B is linked to C");
+    assert!(sim.topology.set_asymmetric_latency(link, Some((0, 50))));
+
+    let topology = sim.topology();
+    assert_eq!(topology.spanning_tree(), [(0, 2), (5, 1)]);
+    assert_eq!(topology.blocked_links(), [(3, 4)]);
+}
+
+#[test]
+fn two_components_are_not_connected() {
+    let topology = sim_setup(nodes![
+        Sender::new(ETH0, ETH1),
+        Receiver::new(ETH1),
+        Sender::new(ETH2, ETH3),
+        Receiver::new(ETH3).nic(ETH4),
+        Receiver::new(ETH5)
+    ])
+    .expect("Sim correctly initializes");
+    assert_eq!(topology.degree(0), 1);
+    assert_eq!(topology.degree(3), 1);
+    assert_eq!(topology.degree(4), 0);
+    assert_eq!(topology.degree(9), 0);
+    assert!(!topology.is_connected());
+
+    let sim = diamond();
+    assert_eq!(sim.topology().degree(0), 2);
+    assert!(sim.topology().is_connected());
+}
+
+#[test]
+fn rings_have_cycles_and_trees_do_not() {
+    let mut nodes = receivers(6, 2);
+    let ring = topology::ring(&mut as_nodes(&mut nodes)).expect("Ring is generated");
+    assert!(ring.has_cycle());
+
+    let mut nodes = receivers(5, 4);
+    let star = topology::star(&mut as_nodes(&mut nodes)).expect("Star is generated");
+    assert!(!star.has_cycle());
+    assert!(diamond().topology().has_cycle());
+}
+
+#[test]
+fn cloned_topology_matches_and_runs_on_its_own() {
+    let mut sender = Sender::new(ETH0, ETH1).latency(10);
+    let mut receiver = Receiver::new(ETH1);
+    let nodes: &mut [&mut dyn Node] = nodes![sender, receiver];
+    let topology = sim_setup(nodes).expect("Sim correctly initializes");
+    let mut clone = topology.clone();
+    assert_eq!(clone.all_nics(), topology.all_nics());
+    assert_eq!(clone.links, topology.links);
+
+    let link = clone.link_between(0, 1).expect("Nodes are linked");
+    // Changing the clone leaves the original be.
+    assert!(clone.set_jitter(link, 5));
+    assert_ne!(clone.links, topology.links);
+    let mut sim = Sim::new(nodes, topology);
+    sim.run().expect("Sim runs to completion");
+    assert_eq!(sim.now(), 10);
+}